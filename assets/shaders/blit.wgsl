@@ -1,8 +1,10 @@
-@group(0) @binding(0) var input: texture_storage_2d<rgba8unorm, read>;
+#include "fullscreen_output.wgsl"
+
+@group(0) @binding(0) var input: texture_2d<f32>;
 
 @fragment
-fn fragment_main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+fn fragment_main(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
 	let texture_dimensions = vec2<f32>(textureDimensions(input));
-	let texel_coords = vec2<u32>(texture_dimensions * uv);
-	return textureLoad(input, texel_coords);
+	let texel_coords = vec2<u32>(texture_dimensions * in.uv);
+	return textureLoad(input, texel_coords, 0);
 }
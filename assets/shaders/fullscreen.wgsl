@@ -1,9 +1,4 @@
-struct FullscreenVertexOutput {
-	@builtin(position)
-	clip_position: vec4<f32>,
-	@location(0)
-	uv: vec2<f32>
-}
+#include "fullscreen_output.wgsl"
 
 @vertex
 fn vertex_main(
@@ -0,0 +1,6 @@
+struct FullscreenVertexOutput {
+	@builtin(position)
+	clip_position: vec4<f32>,
+	@location(0)
+	uv: vec2<f32>
+}
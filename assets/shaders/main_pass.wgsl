@@ -1,6 +1,9 @@
 @group(0) @binding(0) var output: texture_storage_2d<rgba8unorm, write>;
 
-@compute @workgroup_size(16, 16)
+override WORKGROUP_WIDTH: u32 = 16;
+override WORKGROUP_HEIGHT: u32 = 16;
+
+@compute @workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 	let texture_dimensions = textureDimensions(output);
 	let color = vec2<f32>(global_id.xy) / vec2<f32>(texture_dimensions);
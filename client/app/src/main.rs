@@ -1,7 +1,7 @@
 use futures_lite::future;
 
 use glued::{AppRunner, ModularApp};
-use starflow_render::{GpuContextConfig, Renderer, Features};
+use starflow_render::{GpuContextConfig, Renderer, Features, PipelineCompilation};
 use starflow_window::{WindowModule, WinitRunner};
 
 
@@ -22,10 +22,23 @@ impl From<WindowModule> for ClientApp<'_> {
 
 fn create_renderer<'w>(window: &WindowModule) -> Renderer<'w> {
 	let context_config = GpuContextConfig::default()
-		.add_features(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
-	future::block_on(
+		.add_optional_features(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+		.pipeline_cache_dir(std::env::temp_dir().join("starflow"))
+		.pipeline_compilation(PipelineCompilation::parallel());
+	#[cfg(feature="hot-reload")]
+	let context_config = context_config.asset_manifest(
+		concat!(env!("CARGO_MANIFEST_DIR"), "/../../assets/render_assets.ron")
+	);
+	let renderer = future::block_on(
 		Renderer::new(context_config, window.clone_handle())
 	)
+		.unwrap_or_else(|err| panic!("Failed to create renderer: {}", err))
+		.with_resize_events(window.resize_events());
+	#[cfg(feature="hot-reload")]
+	let renderer = renderer.with_shader_hot_reload(
+		concat!(env!("CARGO_MANIFEST_DIR"), "/../../assets/shaders")
+	);
+	renderer
 }
 
 
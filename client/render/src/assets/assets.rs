@@ -1,27 +1,65 @@
-use core::{error, fmt};
-use std::ops::Index;
+use core::{cell::{Cell, RefCell}, marker::PhantomData, mem};
+use std::{
+	collections::{HashMap, HashSet}, ops::Index, path::{Path, PathBuf}, rc::Rc,
+	sync::PoisonError
+};
 use default::default;
 
-use wgpu::Device;
+use futures_lite::future;
+use log::{error, info};
+use wgpu::{BindGroupLayoutEntry, Device, ErrorFilter, PipelineCache, TextureFormat};
 
 use starflow_util::{Handle, Registry};
 
-use crate::core::RenderSurface;
+use crate::{InitError, PipelineCompilation};
+
+use super::{
+	AssetDependencies, AssetError, AssetErrors, AssetId, AssetKind, AssetManifest, AssetResult,
+	CompiledPipeline, CompileResult, PipelineCompiler, ShaderReflection, ShaderSources,
+	ShaderVariant
+};
 
 
 pub struct RenderAssetsCreation<'renderer> {
 	pub(super) assets: &'renderer mut RenderAssets,
-	pub(super) surface: &'renderer RenderSurface<'renderer>,
-	pub(super) device: &'renderer Device
+	/// Format of the final target
+	pub(super) target_format: TextureFormat,
+	pub(super) device: &'renderer Device,
+	pub(super) shaders: &'renderer ShaderSources,
+	/// Dependencies used by asset being created
+	used_dependencies: RefCell<Vec<AssetId>>,
+	/// Reflected by asset being created, stored once it's created
+	metadata: RefCell<AssetMetadata>,
+	/// Assets created by this creation, used to detect duplicate keys
+	created: HashSet<AssetId>,
+	/// Only outdated assets are recreated while reloading
+	reload: Option<AssetReload>
 }
 
 impl<'r> RenderAssetsCreation<'r> {
 	pub(crate) fn new(
 		assets: &'r mut RenderAssets,
-		surface: &'r RenderSurface<'r>,
-		device: &'r Device
+		target_format: TextureFormat,
+		device: &'r Device,
+		shaders: &'r ShaderSources
 	) -> Self {
-		Self { assets, surface, device }
+		Self {
+			assets, target_format, device, shaders,
+			used_dependencies: default(),
+			metadata: default(),
+			created: default(),
+			reload: None
+		}
+	}
+
+	/// Assets are recreated if they were loaded from changed files or their includes,
+	/// invalidated by replacement or built from recreated assets
+	pub(crate) fn reloading(mut self, mut changed_files: HashSet<Box<str>>) -> Self {
+		let evicted = self.assets.evict_shader_variants(&changed_files);
+		changed_files.extend(evicted);
+		let outdated = mem::take(&mut self.assets.outdated);
+		self.reload = Some(AssetReload { changed_files, outdated });
+		self
 	}
 
 	#[allow(private_bounds)]
@@ -30,12 +68,98 @@ impl<'r> RenderAssetsCreation<'r> {
 		D: RenderAssetDesc<'a>,
 		RenderAssets: HasRegistry<D::Asset>
 	{
-		let key = descriptor.key().into();
+		let id = AssetId::new(<D::Asset as sealed::RenderAsset>::KIND, descriptor.key());
+		if !self.created.insert(id.clone()) {
+			return Err(AssetError::DuplicateKey(id));
+		}
+		let previous = self.assets.get_handle(&id.key);
+		if let Some(reload) = &self.reload
+			&& let Some(previous) = &previous
+			&& !reload.is_outdated(&id, &descriptor)
+		{
+			return Ok(*previous);
+		}
+
+		// Previous asset stays in place if the new one fails
+		let asset = self.create_validated(descriptor)?;
+		if let Some(reload) = &mut self.reload {
+			info!("Rebuilt {}", id);
+			reload.outdated.extend(self.assets.dependencies.direct_dependents(&id).cloned());
+		}
+		else if previous.is_some() {
+			info!("Replaced {}, rebuilding dependent assets", id);
+			self.assets.invalidate(&id);
+		}
+		Ok(self.assets.insert(id, asset))
+	}
+
+	/// Pipeline is compiled by worker threads if compilation is asynchronous,
+	/// only errors of resolving and validating its dependencies are returned then
+	#[allow(private_bounds)]
+	pub fn create_pipeline<'a, D>(
+		&mut self,
+		descriptor: D
+	) -> AssetResult<'a, PipelineHandle<D::Asset>>
+	where
+		D: PipelineDesc<'a>,
+		D::Asset: Send + 'static,
+		RenderAssets: HasRegistry<D::Asset>
+	{
+		if self.assets.compiler.is_none() {
+			let key = descriptor.key().to_owned();
+			return self.create(descriptor)
+				.map(|handle| PipelineHandle::ready(&key, handle));
+		}
+
+		let id = AssetId::new(<D::Asset as sealed::RenderAsset>::KIND, descriptor.key());
+		if !self.created.insert(id.clone()) {
+			return Err(AssetError::DuplicateKey(id));
+		}
+		let handle = PipelineHandle::new(&id.key);
+		let previous = self.assets.get_handle::<D::Asset>(&id.key);
+		if let Some(reload) = &self.reload
+			&& previous.is_some()
+			&& !reload.is_outdated(&id, &descriptor)
+		{
+			return Ok(handle);
+		}
+
+		self.used_dependencies.get_mut().clear();
+		*self.metadata.get_mut() = default();
+		let prepared = descriptor.prepare(self)?;
+		let dependencies = mem::take(self.used_dependencies.get_mut());
+		let metadata = mem::take(self.metadata.get_mut());
+		self.assets.compile(id, dependencies, metadata, prepared, previous.is_some());
+		Ok(handle)
+	}
+
+	/// Validation errors are captured with error scope
+	fn create_validated<'a, D>(&mut self, descriptor: D) -> AssetResult<'a, CreatedAsset<D::Asset>>
+	where D: RenderAssetDesc<'a> {
+		let scope_lock = self.assets.compiler.as_ref().map(PipelineCompiler::scope_lock);
+		let _scope_guard = scope_lock.as_deref()
+			.map(|lock| lock.write().unwrap_or_else(PoisonError::into_inner));
+		self.device.push_error_scope(ErrorFilter::Validation);
+		let asset = self.create_recorded(descriptor);
+		let validation_error = future::block_on(self.device.pop_error_scope());
+		match (asset, validation_error) {
+			(Ok(asset), None) => Ok(asset),
+			(Ok(_), Some(err)) => Err(AssetError::Validation(err)),
+			(Err(err), _) => Err(err)
+		}
+	}
+
+	/// Creates asset and records dependencies it was created from
+	fn create_recorded<'a, D>(&mut self, descriptor: D) -> AssetResult<'a, CreatedAsset<D::Asset>>
+	where D: RenderAssetDesc<'a> {
+		self.used_dependencies.get_mut().clear();
+		*self.metadata.get_mut() = default();
 		let asset = descriptor.create(self)?;
-		Ok(self.assets
-			.get_registry_mut()
-			.set(key, asset)
-		)
+		Ok(CreatedAsset {
+			asset,
+			dependencies: mem::take(self.used_dependencies.get_mut()),
+			metadata: mem::take(self.metadata.get_mut())
+		})
 	}
 
 	#[allow(private_bounds)]
@@ -44,29 +168,68 @@ impl<'r> RenderAssetsCreation<'r> {
 		R: sealed::RenderAsset,
 		RenderAssets: HasRegistry<R>
 	{
-		self.assets
-			.get_asset(key)
-			.ok_or(AssetError::MissingDependency(key))
+		let kind = <R as sealed::RenderAsset>::KIND;
+		let Some(asset) = self.assets.get_asset(key) else {
+			return Err(match self.assets.dependencies.kind_of(key) {
+				Some(found) => AssetError::KindMismatch { key: key.into(), expected: kind, found },
+				None => AssetError::MissingDependency(key.into())
+			});
+		};
+		self.used_dependencies
+			.borrow_mut()
+			.push(AssetId::new(kind, key));
+		Ok(asset)
+	}
+
+	/// Reflection of a created shader module, if its source is WGSL
+	pub fn shader_reflection(&self, key: &str) -> Option<Rc<ShaderReflection>> {
+		self.assets.shader_reflection(key)
+	}
+
+	/// Entries of bind group layout being created
+	pub(super) fn set_layout_entries(&self, entries: Vec<BindGroupLayoutEntry>) {
+		self.metadata.borrow_mut().layout_entries = Some(entries);
+	}
+
+	/// Workgroup size of compute pipeline being created
+	pub(super) fn set_workgroup_size(&self, size: Option<[u32; 3]>) {
+		self.metadata.borrow_mut().workgroup_size = size;
 	}
 }
 
 
-pub type AssetResult<'a, R> = Result<R, AssetError<'a>>;
+/// Asset with dependencies and metadata recorded while creating it
+struct CreatedAsset<R> {
+	asset: R,
+	dependencies: Vec<AssetId>,
+	metadata: AssetMetadata
+}
 
-#[derive(Debug)]
-pub enum AssetError<'a> {
-	MissingDependency(&'a str)
+/// Reflected while creating asset, stored along with it
+#[derive(Default)]
+struct AssetMetadata {
+	layout_entries: Option<Vec<BindGroupLayoutEntry>>,
+	/// Removes previous size of compute pipeline if `None`
+	workgroup_size: Option<[u32; 3]>
 }
 
-impl<'a> fmt::Display for AssetError<'a> {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		match self {
-			Self::MissingDependency(dep) => {write!(f, "Missing dependency {}", dep)}
-		}
-	}
+
+/// Changed shader files and assets to be rebuilt
+pub struct AssetReload {
+	changed_files: HashSet<Box<str>>,
+	outdated: HashSet<AssetId>
 }
 
-impl<'a> error::Error for AssetError<'a> {}
+impl AssetReload {
+	pub fn is_file_changed(&self, path: &str) -> bool {
+		self.changed_files.contains(path)
+	}
+
+	fn is_outdated<'a, D>(&self, id: &AssetId, descriptor: &D) -> bool
+	where D: RenderAssetDesc<'a> {
+		self.outdated.contains(id) || descriptor.is_outdated(self)
+	}
+}
 
 
 pub trait RenderAssetDesc<'a> {
@@ -74,16 +237,34 @@ pub trait RenderAssetDesc<'a> {
 
 	fn key(&self) -> &str;
 	fn create(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, Self::Asset>;
+
+	/// Whether asset has to be recreated on reload regardless of its dependencies
+	fn is_outdated(&self, _reload: &AssetReload) -> bool { false }
 }
 
+/// Pipeline descriptor that owns everything needed to compile it on another thread
+pub type PreparedPipeline<P> = Box<dyn FnOnce(&Device) -> P + Send>;
+
+/// Pipelines are prepared on the main thread and can be compiled on worker threads
+pub trait PipelineDesc<'a>: RenderAssetDesc<'a> {
+	/// Resolves and validates dependencies
+	fn prepare(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, PreparedPipeline<Self::Asset>>;
+}
+
+pub(crate) use sealed::RenderAsset;
+
 mod sealed {
-	pub trait RenderAsset {}
+	use crate::assets::AssetKind;
 
-	impl RenderAsset for wgpu::BindGroupLayout {}
-	impl RenderAsset for wgpu::PipelineLayout {}
-	impl RenderAsset for wgpu::ShaderModule {}
-	impl RenderAsset for wgpu::RenderPipeline {}
-	impl RenderAsset for wgpu::ComputePipeline {}
+	pub trait RenderAsset {
+		const KIND: AssetKind;
+	}
+
+	impl RenderAsset for wgpu::BindGroupLayout { const KIND: AssetKind = AssetKind::BindGroupLayout; }
+	impl RenderAsset for wgpu::PipelineLayout { const KIND: AssetKind = AssetKind::PipelineLayout; }
+	impl RenderAsset for wgpu::ShaderModule { const KIND: AssetKind = AssetKind::ShaderModule; }
+	impl RenderAsset for wgpu::RenderPipeline { const KIND: AssetKind = AssetKind::RenderPipeline; }
+	impl RenderAsset for wgpu::ComputePipeline { const KIND: AssetKind = AssetKind::ComputePipeline; }
 }
 
 pub(crate) type BindGroupLayouts = Registry<Box<str>, wgpu::BindGroupLayout>;
@@ -98,10 +279,251 @@ pub(crate) struct RenderAssets {
 	pipeline_layouts: PipelineLayouts,
 	shader_modules: ShaderModules,
 	render_pipelines: RenderPipelines,
-	compute_pipelines: ComputePipelines
+	compute_pipelines: ComputePipelines,
+	dependencies: AssetDependencies,
+	/// Manifest file assets are created from, embedded manifest is used if none
+	manifest_path: Option<PathBuf>,
+	/// Dependents of replaced assets, rebuilt on next reload
+	outdated: HashSet<AssetId>,
+	shader_variants: RefCell<HashMap<ShaderVariant, CachedShader>>,
+	/// Reflections of shader modules by key
+	shader_reflections: RefCell<HashMap<Box<str>, Rc<ShaderReflection>>>,
+	/// Entries of bind group layouts by key, used to validate pipelines against shaders
+	layout_entries: HashMap<Box<str>, Vec<BindGroupLayoutEntry>>,
+	/// Workgroup sizes of compute pipelines by key
+	workgroup_sizes: HashMap<Box<str>, [u32; 3]>,
+	/// Used by pipelines that don't specify their own cache
+	pipeline_cache: Option<PipelineCache>,
+	/// Pipelines are compiled asynchronously if present
+	compiler: Option<PipelineCompiler>,
+	/// Pipelines that are compiling or failed to compile
+	pipeline_states: HashMap<AssetId, CompileState>
+}
+
+enum CompileState {
+	Pending {
+		generation: u64,
+		/// Previous version is kept if compilation fails
+		has_previous: bool
+	},
+	Failed(AssetError<'static>)
+}
+
+struct CachedShader {
+	module: wgpu::ShaderModule,
+	reflection: Option<Rc<ShaderReflection>>,
+	/// Preprocessed file and its includes
+	files: Vec<Box<str>>
 }
 
 impl RenderAssets {
+	#[allow(private_bounds)]
+	fn insert<R>(&mut self, id: AssetId, created: CreatedAsset<R>) -> Handle<R>
+	where
+		R: sealed::RenderAsset,
+		Self: HasRegistry<R>
+	{
+		let CreatedAsset { asset, dependencies, metadata } = created;
+		if let Some(entries) = metadata.layout_entries {
+			self.layout_entries.insert(id.key.clone(), entries);
+		}
+		if R::KIND == AssetKind::ComputePipeline {
+			match metadata.workgroup_size {
+				Some(size) => self.workgroup_sizes.insert(id.key.clone(), size),
+				None => self.workgroup_sizes.remove(&id.key)
+			};
+		}
+		self.outdated.remove(&id);
+		self.dependencies.set(id.clone(), dependencies);
+		self.get_registry_mut().set(id.key, asset)
+	}
+
+	/// Marks all assets built from `asset` to be rebuilt on next reload
+	pub fn invalidate(&mut self, asset: &AssetId) {
+		self.outdated.extend(self.dependencies.dependents(asset));
+	}
+
+	pub fn dependencies(&self) -> &AssetDependencies {
+		&self.dependencies
+	}
+
+	pub fn manifest_path(&self) -> Option<&Path> {
+		self.manifest_path.as_deref()
+	}
+
+	pub fn pipeline_cache(&self) -> Option<&PipelineCache> {
+		self.pipeline_cache.as_ref()
+	}
+
+	/// Handle of pipeline that is created, compiling or failed to compile
+	#[allow(private_bounds)]
+	pub fn pipeline_handle<P>(&self, key: &str) -> Option<PipelineHandle<P>>
+	where
+		P: sealed::RenderAsset,
+		Self: HasRegistry<P>
+	{
+		let id = AssetId::new(P::KIND, key);
+		match self.get_handle(key) {
+			Some(handle) => Some(PipelineHandle::ready(key, handle)),
+			None => self.pipeline_states.contains_key(&id).then(|| PipelineHandle::new(key))
+		}
+	}
+
+	/// Previous version of recompiled pipeline is ready until the new one is compiled
+	#[allow(private_bounds)]
+	pub fn pipeline_state<P>(&self, handle: &PipelineHandle<P>) -> PipelineState<'_, P>
+	where
+		P: sealed::RenderAsset,
+		Self: HasRegistry<P>
+	{
+		let registry = self.get_registry();
+		if let Some(pipeline) = handle.ready.get().and_then(|ready| registry.get_by_handle(ready)) {
+			return PipelineState::Ready(pipeline);
+		}
+		// Handle is resolved again if pipeline was removed and recreated
+		if let Some(ready) = registry.get_handle(&handle.key) {
+			handle.ready.set(Some(ready));
+			return PipelineState::Ready(&registry[ready]);
+		}
+		match self.pipeline_states.get(&AssetId::new(P::KIND, &handle.key)) {
+			Some(CompileState::Failed(err)) => PipelineState::Failed(err),
+			_ => PipelineState::Pending
+		}
+	}
+
+	/// Number of pipelines being compiled, including recompiled ones
+	pub fn pending_pipelines(&self) -> usize {
+		self.pipeline_states.values()
+			.filter(|state| matches!(state, CompileState::Pending { .. }))
+			.count()
+	}
+
+	fn compile<P>(
+		&mut self,
+		id: AssetId,
+		dependencies: Vec<AssetId>,
+		metadata: AssetMetadata,
+		prepared: PreparedPipeline<P>,
+		has_previous: bool
+	)
+	where
+		P: sealed::RenderAsset + Send + 'static,
+		Self: HasRegistry<P>
+	{
+		let Some(compiler) = &mut self.compiler else {
+			return;
+		};
+		let inserted_id = id.clone();
+		let generation = compiler.submit(id.clone(), Box::new(move |device| {
+			let pipeline = prepared(device);
+			Box::new(move |assets: &mut RenderAssets| {
+				assets.insert(inserted_id, CreatedAsset { asset: pipeline, dependencies, metadata });
+			}) as CompiledPipeline
+		}));
+		self.pipeline_states.insert(id, CompileState::Pending { generation, has_previous });
+	}
+
+	/// Inserts pipelines compiled since last call
+	pub(crate) fn receive_compiled_pipelines(&mut self) {
+		while let Some(result) = self.compiler.as_ref().and_then(PipelineCompiler::try_receive) {
+			self.finish_compilation(result);
+		}
+	}
+
+	/// Blocks until all pending pipelines are compiled or failed
+	pub(crate) fn wait_for_pipelines(&mut self) {
+		while self.pending_pipelines() > 0 {
+			let Some(compiler) = &self.compiler else {
+				return;
+			};
+			let result = compiler.receive();
+			self.finish_compilation(result);
+		}
+	}
+
+	fn finish_compilation(&mut self, result: CompileResult) {
+		let has_previous = match self.pipeline_states.get(&result.id) {
+			Some(&CompileState::Pending { generation, has_previous })
+				if generation == result.generation => has_previous,
+			// Superseded by newer compilation
+			_ => return
+		};
+		match result.pipeline {
+			Ok(insert) => {
+				info!("Compiled {}", result.id);
+				self.pipeline_states.remove(&result.id);
+				insert(self);
+			}
+			Err(err) => {
+				let err = AssetError::Validation(err);
+				if has_previous {
+					error!("Failed to rebuild {}, keeping previous version: {}", result.id, err);
+					self.pipeline_states.remove(&result.id);
+				}
+				else {
+					error!("Failed to compile {}: {}", result.id, err);
+					self.pipeline_states.insert(result.id, CompileState::Failed(err));
+				}
+			}
+		}
+	}
+
+	pub(super) fn cached_shader_variant(
+		&self,
+		variant: &ShaderVariant
+	) -> Option<(wgpu::ShaderModule, Option<Rc<ShaderReflection>>)> {
+		self.shader_variants
+			.borrow()
+			.get(variant)
+			.map(|cached| (cached.module.clone(), cached.reflection.clone()))
+	}
+
+	pub(super) fn cache_shader_variant(
+		&self,
+		variant: ShaderVariant,
+		module: wgpu::ShaderModule,
+		reflection: Option<Rc<ShaderReflection>>,
+		files: Vec<Box<str>>
+	) {
+		self.shader_variants
+			.borrow_mut()
+			.insert(variant, CachedShader { module, reflection, files });
+	}
+
+	pub fn shader_reflection(&self, key: &str) -> Option<Rc<ShaderReflection>> {
+		self.shader_reflections.borrow().get(key).cloned()
+	}
+
+	pub(super) fn set_shader_reflection(&self, key: &str, reflection: Option<Rc<ShaderReflection>>) {
+		let mut reflections = self.shader_reflections.borrow_mut();
+		match reflection {
+			Some(reflection) => reflections.insert(key.into(), reflection),
+			None => reflections.remove(key)
+		};
+	}
+
+	pub(crate) fn layout_entries(&self, key: &str) -> Option<&[BindGroupLayoutEntry]> {
+		self.layout_entries.get(key).map(Vec::as_slice)
+	}
+
+	/// Workgroup size of compute pipeline reflected from its shader
+	pub fn workgroup_size(&self, pipeline: &str) -> Option<[u32; 3]> {
+		self.workgroup_sizes.get(pipeline).copied()
+	}
+
+	/// Removes variants using any of changed files, returns paths of removed variants
+	fn evict_shader_variants(&mut self, changed_files: &HashSet<Box<str>>) -> Vec<Box<str>> {
+		let mut evicted = Vec::new();
+		self.shader_variants.get_mut().retain(|variant, cached| {
+			let outdated = cached.files.iter().any(|file| changed_files.contains(file));
+			if outdated {
+				evicted.push(variant.path.clone());
+			}
+			!outdated
+		});
+		evicted
+	}
+
 	#[allow(private_bounds)]
 	pub fn get_handle<R>(&self, key: &str) -> Option<Handle<R>>
 	where
@@ -134,7 +556,7 @@ where
 }
 
 
-trait HasRegistry<A>
+pub(crate) trait HasRegistry<A>
 where A: sealed::RenderAsset {
 	fn get_registry(&self) -> &Registry<Box<str>, A>;
 	fn get_registry_mut(&mut self) -> &mut Registry<Box<str>, A>;
@@ -161,61 +583,327 @@ impl_has_registry!(RenderAssets, wgpu::RenderPipeline, render_pipelines);
 impl_has_registry!(RenderAssets, wgpu::ComputePipeline, compute_pipelines);
 
 
-// TODO: Move this outside renderer with error handling
-pub(crate) fn create_render_assets(surface: &RenderSurface, device: &Device) -> RenderAssets {
-	use wgpu::{ShaderStages, TextureFormat, StorageTextureAccess};
-	use crate::assets::util::binding;
-	use super::desc::*;
+/// Handle of pipeline that may still be compiling
+pub struct PipelineHandle<P> {
+	key: Box<str>,
+	/// Registry handle, set once pipeline is ready
+	ready: Cell<Option<Handle<P>>>,
+	asset: PhantomData<P>
+}
 
-	let mut assets = RenderAssets::default();
-	{
-		let mut ctx = RenderAssetsCreation::new(&mut assets, surface, device);
-
-		ctx.create(BindGroupLayout::new("output_texture", &[
-				binding(0)
-					.visibility(ShaderStages::COMPUTE)
-					.texture_storage_2d(TextureFormat::Rgba8Unorm, StorageTextureAccess::WriteOnly)
-		])).unwrap();
-		ctx.create(BindGroupLayout::new("input_texture", &[
-				binding(0)
-					.visibility(ShaderStages::FRAGMENT)
-					.texture_storage_2d(TextureFormat::Rgba8Unorm, StorageTextureAccess::ReadOnly)
-		])).unwrap();
-		// main pass
-		ctx.create(PipelineLayout {
-			key: "main_pass",
-			bind_group_layouts: &["output_texture"],
-			push_constant_ranges: &[]
-		}).unwrap();
-		ctx.create(ShaderModule::new("main_pass",
-			ShaderSource::Wgsl(include_str!("../../../../assets/shaders/main_pass.wgsl").into())
-		)).unwrap();
-		ctx.create(ComputePipeline {
-			key: "main_pass",
-			layout: Some("main_pass"),
-			module: "main_pass",
-		}).unwrap();
-		// blit
-		ctx.create(ShaderModule::new("fullscreen",
-			ShaderSource::Wgsl(include_str!("../../../../assets/shaders/fullscreen.wgsl").into())
-		)).unwrap();
-		ctx.create(ShaderModule::new("blit",
-			ShaderSource::Wgsl(include_str!("../../../../assets/shaders/blit.wgsl").into())
-		)).unwrap();
-		ctx.create(PipelineLayout {
-			key: "blit",
-			bind_group_layouts: &["input_texture"],
-			push_constant_ranges: &[]
-		}).unwrap();
-		ctx.create(RenderPipeline {
-			key: "blit",
-			layout: Some("blit"),
-			vertex: "fullscreen",
-			fragment: Some("blit"),
-			primitive: default(),
-			depth_stencil: None,
-			multisample: default()
-		}).unwrap();
-	}
-	assets
+impl<P> PipelineHandle<P> {
+	fn new(key: &str) -> Self {
+		Self { key: key.into(), ready: Cell::new(None), asset: PhantomData }
+	}
+
+	fn ready(key: &str, handle: Handle<P>) -> Self {
+		Self { key: key.into(), ready: Cell::new(Some(handle)), asset: PhantomData }
+	}
+
+	pub fn key(&self) -> &str {
+		&self.key
+	}
+}
+
+pub enum PipelineState<'a, P> {
+	Ready(&'a P),
+	/// Pipeline is compiling for the first time
+	Pending,
+	/// Pipeline failed to compile and has no previous version
+	Failed(&'a AssetError<'static>)
+}
+
+impl<P> PipelineState<'_, P> {
+	pub fn is_ready(&self) -> bool {
+		matches!(self, Self::Ready(_))
+	}
+}
+
+
+/// Creation continues past failures to report all of them at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CreationMode {
+	FailFast,
+	ContinueOnError
+}
+
+pub(crate) fn create_render_assets(
+	target_format: TextureFormat,
+	device: &Device,
+	shaders: &ShaderSources,
+	manifest_path: Option<PathBuf>,
+	pipeline_cache: Option<PipelineCache>,
+	compilation: PipelineCompilation
+) -> Result<RenderAssets, InitError> {
+	let manifest = AssetManifest::load_or_embedded(manifest_path.as_deref())
+		.map_err(InitError::Manifest)?;
+	let compiler = match compilation {
+		PipelineCompilation::Blocking => None,
+		PipelineCompilation::Async { workers } => Some(PipelineCompiler::new(device, workers))
+	};
+	let mut assets = RenderAssets { manifest_path, pipeline_cache, compiler, ..default() };
+	let mut ctx = RenderAssetsCreation::new(&mut assets, target_format, device, shaders);
+	manifest
+		.create(&mut ctx, CreationMode::ContinueOnError)
+		.map_err(InitError::Assets)?;
+	Ok(assets)
+}
+
+/// Creates assets with `create`, assets of the manifest built from replaced assets
+/// are recreated afterwards in dependency order
+pub(crate) fn extend_render_assets<R>(
+	assets: &mut RenderAssets,
+	target_format: TextureFormat,
+	device: &Device,
+	shaders: &ShaderSources,
+	create: impl FnOnce(&mut RenderAssetsCreation) -> R
+) -> R {
+	let result = create(&mut RenderAssetsCreation::new(assets, target_format, device, shaders));
+	if !assets.outdated.is_empty() {
+		reload_render_assets(assets, target_format, device, shaders, HashSet::new(), false);
+	}
+	result
+}
+
+/// Recreates shader modules loaded from changed files, outdated assets and their dependents.
+/// Manifest file is loaded again, all assets are recreated if it changed
+pub(crate) fn reload_render_assets(
+	assets: &mut RenderAssets,
+	target_format: TextureFormat,
+	device: &Device,
+	shaders: &ShaderSources,
+	changed_files: HashSet<Box<str>>,
+	manifest_changed: bool
+) {
+	let manifest = match AssetManifest::load_or_embedded(assets.manifest_path()) {
+		Ok(manifest) => manifest,
+		Err(err) => return error!("Keeping previous assets: {}", err)
+	};
+	if manifest_changed {
+		info!("Asset manifest changed, rebuilding all assets");
+		let all = assets.dependencies.assets().cloned().collect::<Vec<_>>();
+		assets.outdated.extend(all);
+	}
+	if let Err(err) = recreate_render_assets(&manifest, assets, target_format, device, shaders, changed_files) {
+		error!("Keeping previous versions: {}", err);
+	}
+}
+
+/// Recreates assets of the manifest affected by changed files or marked as outdated
+fn recreate_render_assets(
+	manifest: &AssetManifest,
+	assets: &mut RenderAssets,
+	target_format: TextureFormat,
+	device: &Device,
+	shaders: &ShaderSources,
+	changed_files: HashSet<Box<str>>
+) -> Result<(), AssetErrors> {
+	let mut ctx = RenderAssetsCreation::new(assets, target_format, device, shaders)
+		.reloading(changed_files);
+	manifest.create(&mut ctx, CreationMode::ContinueOnError)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use core::num::NonZero;
+
+	use wgpu::ShaderStages;
+
+	use crate::{
+		assets::{desc::{BindGroupLayout, ComputePipeline, ShaderModule}, util::binding},
+		core::GpuContext
+	};
+
+	use super::*;
+
+
+	const MANIFEST: &str = r#"(
+		shader_modules: {
+			"compute": (source: File("compute.wgsl")),
+			"other": (source: File("other.wgsl")),
+		},
+		compute_pipelines: {
+			"compute": (module: "compute"),
+			"other": (module: "other"),
+		},
+	)"#;
+
+	const COMPUTE: &str = "@compute @workgroup_size(8)\nfn main() {}";
+	const OTHER: &str = "@compute @workgroup_size(1)\nfn main() {}";
+
+	fn sources(files: &[(&'static str, &'static str)]) -> ShaderSources {
+		ShaderSources::Memory(files.iter().copied().collect())
+	}
+
+	fn create(gpu: &GpuContext, manifest: &AssetManifest, shaders: &ShaderSources) -> RenderAssets {
+		let mut assets = RenderAssets::default();
+		let format = TextureFormat::Rgba8Unorm;
+		let mut ctx = RenderAssetsCreation::new(&mut assets, format, &gpu.device, shaders);
+		manifest.create(&mut ctx, CreationMode::FailFast).unwrap();
+		assets
+	}
+
+	fn reload(
+		gpu: &GpuContext,
+		manifest: &AssetManifest,
+		assets: &mut RenderAssets,
+		shaders: &ShaderSources,
+		changed_file: &str
+	) -> Result<(), AssetErrors> {
+		let changed_files = HashSet::from([changed_file.into()]);
+		let format = TextureFormat::Rgba8Unorm;
+		recreate_render_assets(manifest, assets, format, &gpu.device, shaders, changed_files)
+	}
+
+	fn compute_pipeline(assets: &RenderAssets, key: &str) -> wgpu::ComputePipeline {
+		assets.get_asset::<wgpu::ComputePipeline>(key).unwrap().clone()
+	}
+
+	fn shader_module(assets: &RenderAssets, key: &str) -> wgpu::ShaderModule {
+		assets.get_asset::<wgpu::ShaderModule>(key).unwrap().clone()
+	}
+
+
+	#[test]
+	fn changed_shader_rebuilds_dependent_pipelines() {
+		let Some(gpu) = GpuContext::for_tests() else { return };
+		let manifest = AssetManifest::parse(MANIFEST).unwrap();
+		let shaders = sources(&[("compute.wgsl", COMPUTE), ("other.wgsl", OTHER)]);
+		let mut assets = create(&gpu, &manifest, &shaders);
+		let compute = compute_pipeline(&assets, "compute");
+		let other = compute_pipeline(&assets, "other");
+
+		let changed = sources(&[
+			("compute.wgsl", "@compute @workgroup_size(4)\nfn main() {}"),
+			("other.wgsl", OTHER)
+		]);
+		reload(&gpu, &manifest, &mut assets, &changed, "compute.wgsl").unwrap();
+
+		assert_ne!(compute_pipeline(&assets, "compute"), compute);
+		assert_eq!(assets.workgroup_size("compute"), Some([4, 1, 1]));
+		assert_eq!(compute_pipeline(&assets, "other"), other);
+	}
+
+	#[test]
+	fn broken_edit_keeps_previous_pipeline() {
+		let Some(gpu) = GpuContext::for_tests() else { return };
+		let manifest = AssetManifest::parse(MANIFEST).unwrap();
+		let shaders = sources(&[("compute.wgsl", COMPUTE), ("other.wgsl", OTHER)]);
+		let mut assets = create(&gpu, &manifest, &shaders);
+		let module = shader_module(&assets, "compute");
+		let pipeline = compute_pipeline(&assets, "compute");
+
+		let broken = sources(&[
+			("compute.wgsl", "@compute @workgroup_size(4)\nfn main( {}"),
+			("other.wgsl", OTHER)
+		]);
+		let errors = reload(&gpu, &manifest, &mut assets, &broken, "compute.wgsl").unwrap_err();
+
+		let failed = errors.0.iter()
+			.map(|diagnostic| diagnostic.asset.clone())
+			.collect::<Vec<_>>();
+		assert_eq!(failed, [AssetId::new(AssetKind::ShaderModule, "compute")]);
+		assert_eq!(shader_module(&assets, "compute"), module);
+		assert_eq!(compute_pipeline(&assets, "compute"), pipeline);
+		assert_eq!(assets.workgroup_size("compute"), Some([8, 1, 1]));
+	}
+
+	#[test]
+	fn evicted_shader_variants_are_recompiled() {
+		let manifest = r#"(
+			shader_modules: {
+				"first": (source: File("shader.wgsl")),
+				"second": (source: File("shader.wgsl")),
+				"other": (source: File("other.wgsl")),
+			},
+		)"#;
+		let Some(gpu) = GpuContext::for_tests() else { return };
+		let manifest = AssetManifest::parse(manifest).unwrap();
+		let shader = "#include \"common.wgsl\"\n@compute @workgroup_size(SIZE)\nfn main() {}";
+		let mut assets = create(&gpu, &manifest, &sources(&[
+			("shader.wgsl", shader), ("common.wgsl", "const SIZE = 8u;"), ("other.wgsl", OTHER)
+		]));
+		let first = shader_module(&assets, "first");
+		let other = shader_module(&assets, "other");
+		assert_eq!(shader_module(&assets, "second"), first);
+
+		let changed = sources(&[
+			("shader.wgsl", shader), ("common.wgsl", "const SIZE = 4u;"), ("other.wgsl", OTHER)
+		]);
+		reload(&gpu, &manifest, &mut assets, &changed, "common.wgsl").unwrap();
+
+		let recompiled = shader_module(&assets, "first");
+		assert_ne!(recompiled, first);
+		assert_eq!(shader_module(&assets, "second"), recompiled);
+		assert_eq!(shader_module(&assets, "other"), other);
+	}
+
+	#[test]
+	fn failed_layout_keeps_previous_entries() {
+		let Some(gpu) = GpuContext::for_tests() else { return };
+		let mut assets = RenderAssets::default();
+		let (format, shaders) = (TextureFormat::Rgba8Unorm, ShaderSources::Embedded);
+		let uniform = [binding(0).visibility(ShaderStages::COMPUTE).uniform_buffer()];
+		let storage = binding(0).visibility(ShaderStages::COMPUTE).storage_buffer();
+		let duplicate = [storage, storage];
+
+		let mut ctx = RenderAssetsCreation::new(&mut assets, format, &gpu.device, &shaders);
+		ctx.create(BindGroupLayout::new("layout", &uniform)).unwrap();
+		let mut ctx = RenderAssetsCreation::new(&mut assets, format, &gpu.device, &shaders);
+		let result = ctx.create(BindGroupLayout::new("layout", &duplicate));
+
+		assert!(matches!(result, Err(AssetError::Validation(_))));
+		assert_eq!(assets.layout_entries("layout"), Some(uniform.as_slice()));
+	}
+
+	/// Creates `compute` pipeline with overridden workgroup width
+	fn create_overridden(
+		gpu: &GpuContext,
+		assets: &mut RenderAssets,
+		constants: &[(&str, f64)]
+	) -> AssetResult<'static, ()> {
+		let (format, shaders) = (TextureFormat::Rgba8Unorm, ShaderSources::Embedded);
+		let mut ctx = RenderAssetsCreation::new(assets, format, &gpu.device, &shaders);
+		let source = "override WIDTH: u32 = 8;\n@compute @workgroup_size(WIDTH)\nfn main() {}";
+		ctx.create(ShaderModule::new("compute", wgpu::ShaderSource::Wgsl(source.into())))?;
+		ctx.create_pipeline(ComputePipeline::new("compute", "compute").with_constants(constants))
+			.map(drop)
+			.map_err(AssetError::into_owned)
+	}
+
+	#[test]
+	fn failed_pipeline_keeps_previous_workgroup_size() {
+		let Some(gpu) = GpuContext::for_tests() else { return };
+		let mut assets = RenderAssets::default();
+		create_overridden(&gpu, &mut assets, &[]).unwrap();
+		assert_eq!(assets.workgroup_size("compute"), Some([8, 1, 1]));
+
+		let result = create_overridden(&gpu, &mut assets, &[("WIDTH", 0.0)]);
+		assert!(matches!(result, Err(AssetError::Validation(_))));
+		assert_eq!(assets.workgroup_size("compute"), Some([8, 1, 1]));
+
+		create_overridden(&gpu, &mut assets, &[("WIDTH", 4.0)]).unwrap();
+		assert_eq!(assets.workgroup_size("compute"), Some([4, 1, 1]));
+	}
+
+	#[test]
+	fn workgroup_size_is_recorded_once_pipeline_is_compiled() {
+		let Some(gpu) = GpuContext::for_tests() else { return };
+		let compiler = PipelineCompiler::new(&gpu.device, NonZero::<usize>::MIN);
+		let mut assets = RenderAssets { compiler: Some(compiler), ..default() };
+		create_overridden(&gpu, &mut assets, &[]).unwrap();
+		assert_eq!(assets.workgroup_size("compute"), None);
+		assets.wait_for_pipelines();
+		assert_eq!(assets.workgroup_size("compute"), Some([8, 1, 1]));
+
+		create_overridden(&gpu, &mut assets, &[("WIDTH", 0.0)]).unwrap();
+		assets.wait_for_pipelines();
+		assert_eq!(assets.workgroup_size("compute"), Some([8, 1, 1]));
+
+		create_overridden(&gpu, &mut assets, &[("WIDTH", 4.0)]).unwrap();
+		assert_eq!(assets.workgroup_size("compute"), Some([8, 1, 1]));
+		assets.wait_for_pipelines();
+		assert_eq!(assets.workgroup_size("compute"), Some([4, 1, 1]));
+	}
 }
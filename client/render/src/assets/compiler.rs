@@ -0,0 +1,149 @@
+use core::{cell::Cell, num::NonZero};
+use std::{
+	mem,
+	sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc, Mutex, PoisonError, RwLock},
+	thread
+};
+
+use log::error;
+use wgpu::Device;
+
+use super::{AssetId, RenderAssets};
+
+
+/// Inserts compiled pipeline into assets
+pub(super) type CompiledPipeline = Box<dyn FnOnce(&mut RenderAssets) + Send>;
+
+pub(super) struct CompileJob {
+	pub id: AssetId,
+	/// Results of superseded jobs are discarded
+	pub generation: u64,
+	pub compile: Box<dyn FnOnce(&Device) -> CompiledPipeline + Send>
+}
+
+pub(super) struct CompileResult {
+	pub id: AssetId,
+	pub generation: u64,
+	pub pipeline: Result<CompiledPipeline, wgpu::Error>
+}
+
+thread_local! {
+	static IS_WORKER: Cell<bool> = const { Cell::new(false) };
+	/// First error raised by worker thread while compiling current job
+	static CAPTURED_ERROR: Cell<Option<wgpu::Error>> = const { Cell::new(None) };
+}
+
+/// Compiles pipelines on worker threads.
+/// Error scopes are shared by all threads using the device, so workers don't use them.
+/// Errors are captured by uncaptured error handler on the thread that raised them instead,
+/// while scoped creation on other threads waits for running compilations
+pub(super) struct PipelineCompiler {
+	/// Workers stop when it's dropped
+	jobs: Option<mpsc::Sender<CompileJob>>,
+	/// Queued jobs are discarded once set
+	stopped: Arc<AtomicBool>,
+	workers: Vec<thread::JoinHandle<()>>,
+	results: mpsc::Receiver<CompileResult>,
+	/// Held for reading by compiling workers and for writing while error scope is pushed
+	scope_lock: Arc<RwLock<()>>,
+	next_generation: u64
+}
+
+impl PipelineCompiler {
+	pub fn new(device: &Device, workers: NonZero<usize>) -> Self {
+		device.on_uncaptured_error(Box::new(|err| {
+			if IS_WORKER.get() {
+				let first = CAPTURED_ERROR.take().unwrap_or(err);
+				CAPTURED_ERROR.set(Some(first));
+			}
+			else {
+				// Default handler of wgpu
+				error!("Handling wgpu errors as fatal by default");
+				panic!("wgpu error: {}\n", err);
+			}
+		}));
+
+		let (jobs, job_receiver) = mpsc::channel::<CompileJob>();
+		let (result_sender, results) = mpsc::channel();
+		let job_receiver = Arc::new(Mutex::new(job_receiver));
+		let scope_lock = Arc::new(RwLock::new(()));
+		let stopped = Arc::new(AtomicBool::new(false));
+		let workers = (0..workers.get()).map(|index| {
+			let device = device.clone();
+			let stopped = stopped.clone();
+			let job_receiver = job_receiver.clone();
+			let result_sender = result_sender.clone();
+			let scope_lock = scope_lock.clone();
+			let worker = move || {
+				IS_WORKER.set(true);
+				loop {
+					let job = job_receiver.lock()
+						.unwrap_or_else(PoisonError::into_inner)
+						.recv();
+					// Compiler was dropped
+					let Ok(job) = job else { break };
+					if stopped.load(Ordering::Relaxed) {
+						break;
+					}
+					let guard = scope_lock.read().unwrap_or_else(PoisonError::into_inner);
+					let pipeline = (job.compile)(&device);
+					drop(guard);
+					let pipeline = match CAPTURED_ERROR.take() {
+						Some(err) => Err(err),
+						None => Ok(pipeline)
+					};
+					let result = CompileResult { id: job.id, generation: job.generation, pipeline };
+					if result_sender.send(result).is_err() {
+						break;
+					}
+				}
+			};
+			thread::Builder::new()
+				.name(format!("pipeline-compiler-{}", index))
+				.spawn(worker)
+				.expect("Failed to spawn pipeline compiler thread")
+		}).collect();
+
+		Self { jobs: Some(jobs), stopped, workers, results, scope_lock, next_generation: 0 }
+	}
+
+	/// Returns generation of the job
+	pub fn submit(
+		&mut self,
+		id: AssetId,
+		compile: Box<dyn FnOnce(&Device) -> CompiledPipeline + Send>
+	) -> u64 {
+		let generation = self.next_generation;
+		self.next_generation += 1;
+		self.jobs
+			.as_ref()
+			.and_then(|jobs| jobs.send(CompileJob { id, generation, compile }).ok())
+			.expect("Pipeline compiler threads stopped");
+		generation
+	}
+
+	pub fn try_receive(&self) -> Option<CompileResult> {
+		self.results.try_recv().ok()
+	}
+
+	pub fn receive(&self) -> CompileResult {
+		self.results.recv().expect("Pipeline compiler threads stopped")
+	}
+
+	/// Must be locked for writing while error scope is pushed,
+	/// otherwise the scope would capture errors of compiling workers
+	pub fn scope_lock(&self) -> Arc<RwLock<()>> {
+		self.scope_lock.clone()
+	}
+}
+
+/// Waits for compilations in progress, queued jobs are discarded
+impl Drop for PipelineCompiler {
+	fn drop(&mut self) {
+		self.stopped.store(true, Ordering::Relaxed);
+		drop(self.jobs.take());
+		for worker in mem::take(&mut self.workers) {
+			let _ = worker.join();
+		}
+	}
+}
@@ -0,0 +1,100 @@
+use core::fmt;
+use std::collections::{HashMap, HashSet};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+	BindGroupLayout,
+	PipelineLayout,
+	ShaderModule,
+	RenderPipeline,
+	ComputePipeline
+}
+
+impl fmt::Display for AssetKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::BindGroupLayout => "bind group layout",
+			Self::PipelineLayout => "pipeline layout",
+			Self::ShaderModule => "shader module",
+			Self::RenderPipeline => "render pipeline",
+			Self::ComputePipeline => "compute pipeline"
+		})
+	}
+}
+
+
+/// Keys are unique only within assets of the same kind
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AssetId {
+	pub kind: AssetKind,
+	pub key: Box<str>
+}
+
+impl AssetId {
+	pub fn new(kind: AssetKind, key: &str) -> Self {
+		Self { kind, key: key.into() }
+	}
+}
+
+impl fmt::Display for AssetId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} {}", self.kind, self.key)
+	}
+}
+
+
+/// Assets each asset was created from, recorded during creation
+#[derive(Default)]
+pub struct AssetDependencies {
+	dependencies: HashMap<AssetId, Vec<AssetId>>
+}
+
+impl AssetDependencies {
+	pub(super) fn set(&mut self, asset: AssetId, dependencies: Vec<AssetId>) {
+		self.dependencies.insert(asset, dependencies);
+	}
+
+	/// All created assets
+	pub fn assets(&self) -> impl Iterator<Item = &AssetId> {
+		self.dependencies.keys()
+	}
+
+	/// Kind of any created asset with given key
+	pub fn kind_of(&self, key: &str) -> Option<AssetKind> {
+		self.dependencies.keys()
+			.find(|asset| &*asset.key == key)
+			.map(|asset| asset.kind)
+	}
+
+	pub fn dependencies(&self, asset: &AssetId) -> &[AssetId] {
+		self.dependencies
+			.get(asset)
+			.map_or(&[], Vec::as_slice)
+	}
+
+	/// Assets created directly from `asset`
+	pub fn direct_dependents<'s>(&'s self, asset: &'s AssetId) -> impl Iterator<Item = &'s AssetId> {
+		self.dependencies.iter()
+			.filter(move |(_, dependencies)| dependencies.contains(asset))
+			.map(|(dependent, _)| dependent)
+	}
+
+	/// All assets built from `asset`, each dependent comes after its dependencies
+	pub fn dependents(&self, asset: &AssetId) -> Vec<AssetId> {
+		let mut visited = HashSet::new();
+		let mut order = Vec::new();
+		self.visit_dependents(asset, &mut visited, &mut order);
+		order.reverse();
+		order
+	}
+
+	fn visit_dependents(&self, asset: &AssetId, visited: &mut HashSet<AssetId>, order: &mut Vec<AssetId>) {
+		for dependent in self.direct_dependents(asset) {
+			if visited.insert(dependent.clone()) {
+				self.visit_dependents(dependent, visited, order);
+				order.push(dependent.clone());
+			}
+		}
+	}
+}
@@ -1,15 +1,25 @@
+use std::rc::Rc;
 use default::default;
 
-pub use wgpu::{PushConstantRange, ShaderSource, PrimitiveState, DepthStencilState, MultisampleState};
+use futures_lite::future;
+pub use wgpu::{
+	BlendState, ColorWrites, DepthStencilState, MultisampleState, PipelineCache, PrimitiveState,
+	PushConstantRange, ShaderSource, TextureFormat, VertexBufferLayout
+};
 use wgpu::{
-	BindGroupLayoutDescriptor, BindGroupLayoutEntry, BlendState, ColorTargetState, ColorWrites,
-	ComputePipelineDescriptor, FragmentState, PipelineLayoutDescriptor, RenderPipelineDescriptor,
-	ShaderModuleDescriptor, VertexState
+	BindGroupLayoutDescriptor, BindGroupLayoutEntry, ColorTargetState, ComputePipelineDescriptor,
+	FragmentState, PipelineCompilationOptions, PipelineLayoutDescriptor, RenderPipelineDescriptor,
+	ShaderModuleDescriptor, ShaderStages, VertexState
 };
 
-use crate::assets::AssetResult;
+use crate::assets::{
+	preprocess, reflect_group, validate_group, validate_missing_group, AssetError, AssetId,
+	AssetKind, AssetResult, ShaderCompilationError, ShaderDefines, ShaderReflection, ShaderVariant
+};
 
-use super::{RenderAssetDesc, RenderAssetsCreation};
+use super::{
+	AssetReload, PipelineDesc, PreparedPipeline, RenderAssetDesc, RenderAssetsCreation
+};
 
 
 pub struct BindGroupLayout<'a> {
@@ -32,6 +42,7 @@ impl<'a> RenderAssetDesc<'a> for BindGroupLayout<'a> {
 	fn key(&self) -> &str { &self.key }
 
 	fn create(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, Self::Asset> {
+		ctx.set_layout_entries(self.entries.to_vec());
 		Ok(ctx.device.create_bind_group_layout(&BindGroupLayoutDescriptor {
 			label: Some(self.key),
 			entries: self.entries
@@ -40,6 +51,43 @@ impl<'a> RenderAssetDesc<'a> for BindGroupLayout<'a> {
 }
 
 
+/// Bind group layout of a group declared by shaders, entries are visible
+/// to stages of entry points using them
+pub struct ReflectedBindGroupLayout<'a> {
+	pub key: &'a str,
+	pub shaders: &'a [&'a str],
+	pub group: u32
+}
+
+impl<'a> RenderAssetDesc<'a> for ReflectedBindGroupLayout<'a> {
+	type Asset = wgpu::BindGroupLayout;
+
+	fn key(&self) -> &str { self.key }
+
+	fn create(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, Self::Asset> {
+		let reflections = self.shaders.iter()
+			.map(|&shader| {
+				ctx.get_dependency_asset::<wgpu::ShaderModule>(shader)?;
+				ctx.shader_reflection(shader)
+					.map(|reflection| (shader, reflection))
+					.ok_or(AssetError::MissingReflection(shader.into()))
+			})
+			.collect::<AssetResult<'a, Vec<_>>>()?;
+		let entries = reflect_group(
+			reflections.iter().map(|(shader, reflection)| (*shader, reflection.as_ref())),
+			self.group
+		).map_err(|err| AssetError::LayoutMismatch(Box::new(err)))?;
+
+		let layout = ctx.device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+			label: Some(self.key),
+			entries: &entries
+		});
+		ctx.set_layout_entries(entries);
+		Ok(layout)
+	}
+}
+
+
 pub struct PipelineLayout<'a> {
 	pub key: &'a str,
 	pub bind_group_layouts: &'a [&'a str],
@@ -81,99 +129,471 @@ impl<'a> RenderAssetDesc<'a> for ShaderModule<'a> {
 	fn key(&self) -> &str { &self.key }
 
 	fn create(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, Self::Asset> {
-		Ok(ctx.device.create_shader_module(ShaderModuleDescriptor {
-			label: Some(self.key),
-			source: self.source
-		}))
+		let text = match &self.source {
+			ShaderSource::Wgsl(source) => Some(source.clone()),
+			_ => None
+		};
+		create_shader_module(ctx, self.key, self.source, text.as_deref(), |line| Some((self.key, line)))
+	}
+}
+
+
+/// Shader module loaded from WGSL file in `assets/shaders`, recreated when the file
+/// or its includes change. Variants with equal defines are shared between keys
+pub struct ShaderFile<'a> {
+	pub key: &'a str,
+	pub path: &'a str,
+	pub defines: ShaderDefines
+}
+
+impl<'a> ShaderFile<'a> {
+	pub fn new(key: &'a str, path: &'a str) -> Self {
+		Self { key, path, defines: default() }
+	}
+
+	pub fn with_defines(mut self, defines: ShaderDefines) -> Self {
+		self.defines = defines;
+		self
 	}
 }
 
+impl<'a> RenderAssetDesc<'a> for ShaderFile<'a> {
+	type Asset = wgpu::ShaderModule;
+
+	fn key(&self) -> &str { self.key }
+
+	fn create(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, Self::Asset> {
+		let variant = ShaderVariant { path: self.path.into(), defines: self.defines };
+		if let Some((module, reflection)) = ctx.assets.cached_shader_variant(&variant) {
+			ctx.assets.set_shader_reflection(self.key, reflection);
+			return Ok(module);
+		}
+		let shader = preprocess(ctx.shaders, &variant)
+			.map_err(|err| AssetError::Preprocess(Box::new(err)))?;
+		let module = create_shader_module(
+			ctx,
+			self.key,
+			ShaderSource::Wgsl(shader.source.as_str().into()),
+			Some(&shader.source),
+			|line| shader.origin(line)
+		)?;
+		let reflection = ctx.shader_reflection(self.key);
+		ctx.assets.cache_shader_variant(variant, module.clone(), reflection, shader.files);
+		Ok(module)
+	}
+
+	fn is_outdated(&self, reload: &AssetReload) -> bool {
+		reload.is_file_changed(self.path)
+	}
+}
+
+
+/// Compilation errors are reported with spans in `text`,
+/// `origin` maps lines of `text` to original file and line.
+/// Module is reflected from `text` if it compiles
+fn create_shader_module<'a, 's>(
+	ctx: &RenderAssetsCreation,
+	key: &str,
+	source: ShaderSource,
+	text: Option<&str>,
+	origin: impl Fn(u32) -> Option<(&'s str, u32)>
+) -> AssetResult<'a, wgpu::ShaderModule> {
+	let module = ctx.device.create_shader_module(ShaderModuleDescriptor {
+		label: Some(key),
+		source
+	});
+	let info = future::block_on(module.get_compilation_info());
+	if let Some(err) = ShaderCompilationError::new(key, text, origin, info) {
+		return Err(AssetError::ShaderCompilation(Box::new(err)));
+	}
+	let reflection = text
+		.and_then(ShaderReflection::from_wgsl)
+		.map(Rc::new);
+	ctx.assets.set_shader_reflection(key, reflection);
+	Ok(module)
+}
+
+/// Checks bind group layouts of pipeline layout against bindings used by shader stages
+fn validate_layout<'a>(
+	ctx: &RenderAssetsCreation,
+	layout: &str,
+	shader: &str,
+	stages: ShaderStages
+) -> AssetResult<'a, ()> {
+	let Some(reflection) = ctx.shader_reflection(shader) else {
+		return Ok(());
+	};
+	let bind_group_layouts = ctx.assets
+		.dependencies()
+		.dependencies(&AssetId::new(AssetKind::PipelineLayout, layout));
+	for (group, bind_group_layout) in (0..).zip(bind_group_layouts) {
+		if let Some(entries) = ctx.assets.layout_entries(&bind_group_layout.key) {
+			validate_group(shader, &reflection, group, stages, entries)
+				.map_err(|err| AssetError::LayoutMismatch(Box::new(err)))?;
+		}
+	}
+	for group in bind_group_layouts.len() as u32..reflection.group_count() {
+		validate_missing_group(shader, &reflection, group, stages)
+			.map_err(|err| AssetError::LayoutMismatch(Box::new(err)))?;
+	}
+	Ok(())
+}
+
 
 pub struct ComputePipeline<'a> {
 	pub key: &'a str,
 	pub layout: Option<&'a str>,
-	pub module: &'a str
+	pub module: &'a str,
+	/// Only compute entry point of the module if `None`
+	pub entry_point: Option<&'a str>,
+	/// Values of `override` declarations by name or id
+	pub constants: &'a [(&'a str, f64)],
+	pub cache: Option<&'a PipelineCache>
+}
+
+impl<'a> ComputePipeline<'a> {
+	pub fn new(key: &'a str, module: &'a str) -> Self {
+		Self { key, layout: None, module, entry_point: None, constants: &[], cache: None }
+	}
+
+	pub fn with_layout(mut self, layout: &'a str) -> Self {
+		self.layout = Some(layout);
+		self
+	}
+
+	pub fn with_entry_point(mut self, entry_point: &'a str) -> Self {
+		self.entry_point = Some(entry_point);
+		self
+	}
+
+	pub fn with_constants(mut self, constants: &'a [(&'a str, f64)]) -> Self {
+		self.constants = constants;
+		self
+	}
+
+	pub fn with_cache(mut self, cache: &'a PipelineCache) -> Self {
+		self.cache = Some(cache);
+		self
+	}
 }
 
 impl<'a> RenderAssetDesc<'a> for ComputePipeline<'a> {
 	type Asset = wgpu::ComputePipeline;
 
-	fn key(&self) -> &str { &self.key }
+	fn key(&self) -> &str { self.key }
 
 	fn create(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, Self::Asset> {
-		let layout = self.layout
-			.map(|layout| ctx.get_dependency_asset(layout))
-			.transpose()?;
-		let module = ctx.get_dependency_asset(self.module)?;
+		Ok(self.prepare(ctx)?(ctx.device))
+	}
+}
 
-		Ok(ctx.device.create_compute_pipeline(&ComputePipelineDescriptor {
-			label: Some(self.key),
-			layout,
-			module,
-			entry_point: None,
-			// TODO: More granular control (if needed, otherwise remove this todo in future :))
-			compilation_options: default(),
-			cache: None
-		}))
+impl<'a> PipelineDesc<'a> for ComputePipeline<'a> {
+	fn prepare(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, PreparedPipeline<Self::Asset>> {
+		let layout = self.layout
+			.map(|layout| ctx.get_dependency_asset::<wgpu::PipelineLayout>(layout))
+			.transpose()?
+			.cloned();
+		let module = ctx.get_dependency_asset::<wgpu::ShaderModule>(self.module)?.clone();
+		if let Some(layout) = self.layout {
+			validate_layout(ctx, layout, self.module, ShaderStages::COMPUTE)?;
+		}
+
+		let workgroup_size = ctx.shader_reflection(self.module)
+			.and_then(|reflection| reflection
+				.entry_point(ShaderStages::COMPUTE, self.entry_point)
+				.map(|entry_point| entry_point.specialized_workgroup_size(self.constants)));
+		ctx.set_workgroup_size(workgroup_size);
+
+		let key = self.key.to_owned();
+		let entry_point = self.entry_point.map(str::to_owned);
+		let constants = OwnedConstants::new(self.constants);
+		let cache = self.cache.or(ctx.assets.pipeline_cache()).cloned();
+		Ok(Box::new(move |device| device.create_compute_pipeline(&ComputePipelineDescriptor {
+			label: Some(&key),
+			layout: layout.as_ref(),
+			module: &module,
+			entry_point: entry_point.as_deref(),
+			compilation_options: PipelineCompilationOptions {
+				constants: &constants.borrowed(),
+				..default()
+			},
+			cache: cache.as_ref()
+		})))
 	}
 }
 
 
-// TODO: This is proof of concept. Add more granular control over shaders and other parameters
 pub struct RenderPipeline<'a> {
 	pub key: &'a str,
 	pub layout: Option<&'a str>,
-	pub vertex: &'a str,
-	pub fragment: Option<&'a str>,
+	pub vertex: VertexStage<'a>,
+	pub fragment: Option<FragmentStage<'a>>,
 	pub primitive: PrimitiveState,
 	pub depth_stencil: Option<DepthStencilState>,
-	pub multisample: MultisampleState
+	pub multisample: MultisampleState,
+	pub cache: Option<&'a PipelineCache>
+}
+
+impl<'a> RenderPipeline<'a> {
+	pub fn new(key: &'a str, vertex: VertexStage<'a>) -> Self {
+		Self {
+			key,
+			layout: None,
+			vertex,
+			fragment: None,
+			primitive: default(),
+			depth_stencil: None,
+			multisample: default(),
+			cache: None
+		}
+	}
+
+	pub fn with_layout(mut self, layout: &'a str) -> Self {
+		self.layout = Some(layout);
+		self
+	}
+
+	pub fn with_fragment(mut self, fragment: FragmentStage<'a>) -> Self {
+		self.fragment = Some(fragment);
+		self
+	}
+
+	pub fn with_primitive(mut self, primitive: PrimitiveState) -> Self {
+		self.primitive = primitive;
+		self
+	}
+
+	pub fn with_depth_stencil(mut self, depth_stencil: DepthStencilState) -> Self {
+		self.depth_stencil = Some(depth_stencil);
+		self
+	}
+
+	pub fn with_multisample(mut self, multisample: MultisampleState) -> Self {
+		self.multisample = multisample;
+		self
+	}
+
+	pub fn with_cache(mut self, cache: &'a PipelineCache) -> Self {
+		self.cache = Some(cache);
+		self
+	}
 }
 
 impl<'a> RenderAssetDesc<'a> for RenderPipeline<'a> {
 	type Asset = wgpu::RenderPipeline;
 
-	fn key(&self) -> &str { &self.key }
+	fn key(&self) -> &str { self.key }
 
 	fn create(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, Self::Asset> {
-		let layout = self.layout
-			.map(|layout| ctx.get_dependency_asset(layout))
-			.transpose()?;
-
-		let vertex = ctx.get_dependency_asset(self.vertex)?;
+		Ok(self.prepare(ctx)?(ctx.device))
+	}
+}
 
-		let fragment = match self.fragment {
+impl<'a> PipelineDesc<'a> for RenderPipeline<'a> {
+	fn prepare(self, ctx: &RenderAssetsCreation) -> AssetResult<'a, PreparedPipeline<Self::Asset>> {
+		let layout = self.layout
+			.map(|layout| ctx.get_dependency_asset::<wgpu::PipelineLayout>(layout))
+			.transpose()?
+			.cloned();
+
+		let vertex = &self.vertex;
+		let vertex_module = ctx.get_dependency_asset::<wgpu::ShaderModule>(vertex.module)?.clone();
+		if let Some(layout) = self.layout {
+			validate_layout(ctx, layout, vertex.module, ShaderStages::VERTEX)?;
+		}
+		let vertex_entry_point = vertex.entry_point.map(str::to_owned);
+		let vertex_constants = OwnedConstants::new(vertex.constants);
+		let vertex_buffers = vertex.buffers.iter()
+			.map(|buffer| (buffer.array_stride, buffer.step_mode, buffer.attributes.to_vec()))
+			.collect::<Vec<_>>();
+
+		let fragment = match &self.fragment {
 			Some(fragment) => {
-				let fragment = ctx.get_dependency_asset(fragment)?;
-				Some(FragmentState {
-					module: fragment,
-					entry_point: None,
-					compilation_options: default(),
-					targets: &[Some(ColorTargetState {
-						format: ctx.surface.texture_format(),
-						blend: Some(BlendState::REPLACE),
-						write_mask: ColorWrites::ALL,
-					})]
-				})
+				let module = ctx.get_dependency_asset::<wgpu::ShaderModule>(fragment.module)?.clone();
+				if let Some(layout) = self.layout {
+					validate_layout(ctx, layout, fragment.module, ShaderStages::FRAGMENT)?;
+				}
+				let targets = fragment.targets.iter()
+					.map(|target| target.map(|target| target.state(ctx.target_format)))
+					.collect::<Vec<_>>();
+				Some((
+					module,
+					fragment.entry_point.map(str::to_owned),
+					OwnedConstants::new(fragment.constants),
+					targets
+				))
 			}
 			None => None
 		};
 
-		Ok(ctx.device.create_render_pipeline(&RenderPipelineDescriptor {
-			label: Some(self.key),
-			layout,
-			vertex: VertexState {
-				module: vertex,
-				entry_point: None,
-				compilation_options: default(),
-				buffers: &[]
-			},
-			fragment,
-			primitive: self.primitive,
-			depth_stencil: self.depth_stencil,
-			multisample: self.multisample,
-			multiview: None,
-			cache: None
+		let key = self.key.to_owned();
+		let primitive = self.primitive;
+		let depth_stencil = self.depth_stencil;
+		let multisample = self.multisample;
+		let cache = self.cache.or(ctx.assets.pipeline_cache()).cloned();
+		Ok(Box::new(move |device| {
+			let vertex_buffers = vertex_buffers.iter()
+				.map(|(array_stride, step_mode, attributes)| VertexBufferLayout {
+					array_stride: *array_stride,
+					step_mode: *step_mode,
+					attributes
+				})
+				.collect::<Vec<_>>();
+			let fragment_constants = fragment.as_ref()
+				.map(|(_, _, constants, _)| constants.borrowed())
+				.unwrap_or_default();
+			let fragment = fragment.as_ref()
+				.map(|(module, entry_point, _, targets)| FragmentState {
+					module,
+					entry_point: entry_point.as_deref(),
+					compilation_options: PipelineCompilationOptions {
+						constants: &fragment_constants,
+						..default()
+					},
+					targets
+				});
+
+			device.create_render_pipeline(&RenderPipelineDescriptor {
+				label: Some(&key),
+				layout: layout.as_ref(),
+				vertex: VertexState {
+					module: &vertex_module,
+					entry_point: vertex_entry_point.as_deref(),
+					compilation_options: PipelineCompilationOptions {
+						constants: &vertex_constants.borrowed(),
+						..default()
+					},
+					buffers: &vertex_buffers
+				},
+				fragment,
+				primitive,
+				depth_stencil,
+				multisample,
+				multiview: None,
+				cache: cache.as_ref()
+			})
 		}))
 	}
 }
+
+
+/// Override constants owned by prepared pipeline
+struct OwnedConstants(Vec<(String, f64)>);
+
+impl OwnedConstants {
+	fn new(constants: &[(&str, f64)]) -> Self {
+		Self(constants.iter()
+			.map(|&(name, value)| (name.to_owned(), value))
+			.collect())
+	}
+
+	fn borrowed(&self) -> Vec<(&str, f64)> {
+		self.0.iter()
+			.map(|(name, value)| (name.as_str(), *value))
+			.collect()
+	}
+}
+
+
+pub struct VertexStage<'a> {
+	pub module: &'a str,
+	/// Only vertex entry point of the module if `None`
+	pub entry_point: Option<&'a str>,
+	/// Values of `override` declarations by name or id
+	pub constants: &'a [(&'a str, f64)],
+	pub buffers: &'a [VertexBufferLayout<'a>]
+}
+
+impl<'a> VertexStage<'a> {
+	pub fn new(module: &'a str) -> Self {
+		Self { module, entry_point: None, constants: &[], buffers: &[] }
+	}
+
+	pub fn with_entry_point(mut self, entry_point: &'a str) -> Self {
+		self.entry_point = Some(entry_point);
+		self
+	}
+
+	pub fn with_constants(mut self, constants: &'a [(&'a str, f64)]) -> Self {
+		self.constants = constants;
+		self
+	}
+
+	pub fn with_buffers(mut self, buffers: &'a [VertexBufferLayout<'a>]) -> Self {
+		self.buffers = buffers;
+		self
+	}
+}
+
+
+pub struct FragmentStage<'a> {
+	pub module: &'a str,
+	/// Only fragment entry point of the module if `None`
+	pub entry_point: Option<&'a str>,
+	/// Values of `override` declarations by name or id
+	pub constants: &'a [(&'a str, f64)],
+	/// Targets by `@location`, `None` leaves the location unused
+	pub targets: &'a [Option<ColorTarget>]
+}
+
+impl<'a> FragmentStage<'a> {
+	/// Writes the final target format without blending
+	pub fn new(module: &'a str) -> Self {
+		Self {
+			module,
+			entry_point: None,
+			constants: &[],
+			targets: &[Some(ColorTarget::FINAL_TARGET)]
+		}
+	}
+
+	pub fn with_entry_point(mut self, entry_point: &'a str) -> Self {
+		self.entry_point = Some(entry_point);
+		self
+	}
+
+	pub fn with_constants(mut self, constants: &'a [(&'a str, f64)]) -> Self {
+		self.constants = constants;
+		self
+	}
+
+	pub fn with_targets(mut self, targets: &'a [Option<ColorTarget>]) -> Self {
+		self.targets = targets;
+		self
+	}
+}
+
+
+/// Color target state, format of the final target is used if `format` is `None`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorTarget {
+	pub format: Option<TextureFormat>,
+	pub blend: Option<BlendState>,
+	pub write_mask: ColorWrites
+}
+
+impl ColorTarget {
+	pub const FINAL_TARGET: Self = Self { format: None, blend: None, write_mask: ColorWrites::ALL };
+
+	pub const fn format(format: TextureFormat) -> Self {
+		Self { format: Some(format), ..Self::FINAL_TARGET }
+	}
+
+	pub const fn with_blend(mut self, blend: BlendState) -> Self {
+		self.blend = Some(blend);
+		self
+	}
+
+	pub const fn with_write_mask(mut self, write_mask: ColorWrites) -> Self {
+		self.write_mask = write_mask;
+		self
+	}
+
+	fn state(self, target_format: TextureFormat) -> ColorTargetState {
+		ColorTargetState {
+			format: self.format.unwrap_or(target_format),
+			blend: self.blend,
+			write_mask: self.write_mask
+		}
+	}
+}
@@ -0,0 +1,179 @@
+use core::{error, fmt};
+use std::borrow::Cow;
+
+use wgpu::{CompilationInfo, CompilationMessageType};
+
+use super::{AssetId, AssetKind, LayoutMismatch, PreprocessError};
+
+
+pub type AssetResult<'a, R> = Result<R, AssetError<'a>>;
+
+#[derive(Debug)]
+pub enum AssetError<'a> {
+	MissingDependency(Cow<'a, str>),
+	/// Dependency key exists only for another asset kind
+	KindMismatch {
+		key: Cow<'a, str>,
+		expected: AssetKind,
+		found: AssetKind
+	},
+	/// Asset with the same kind and key was already created
+	DuplicateKey(AssetId),
+	Preprocess(Box<PreprocessError>),
+	ShaderCompilation(Box<ShaderCompilationError>),
+	/// Shader module has no WGSL source to reflect layouts from
+	MissingReflection(Cow<'a, str>),
+	/// Layout doesn't match bindings declared by shader
+	LayoutMismatch(Box<LayoutMismatch>),
+	/// Validation error captured by wgpu error scope
+	Validation(wgpu::Error)
+}
+
+impl AssetError<'_> {
+	pub fn into_owned(self) -> AssetError<'static> {
+		match self {
+			Self::MissingDependency(key) => AssetError::MissingDependency(key.into_owned().into()),
+			Self::KindMismatch { key, expected, found } => AssetError::KindMismatch {
+				key: key.into_owned().into(), expected, found
+			},
+			Self::DuplicateKey(asset) => AssetError::DuplicateKey(asset),
+			Self::Preprocess(err) => AssetError::Preprocess(err),
+			Self::ShaderCompilation(err) => AssetError::ShaderCompilation(err),
+			Self::MissingReflection(key) => AssetError::MissingReflection(key.into_owned().into()),
+			Self::LayoutMismatch(err) => AssetError::LayoutMismatch(err),
+			Self::Validation(err) => AssetError::Validation(err)
+		}
+	}
+}
+
+impl<'a> fmt::Display for AssetError<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::MissingDependency(dep) => {write!(f, "Missing dependency {}", dep)}
+			Self::KindMismatch { key, expected, found } => {
+				write!(f, "Dependency {} is {}, expected {}", key, found, expected)
+			}
+			Self::DuplicateKey(asset) => {write!(f, "Duplicate {}", asset)}
+			Self::Preprocess(err) => {write!(f, "{}", err)}
+			Self::ShaderCompilation(err) => {write!(f, "{}", err)}
+			Self::MissingReflection(key) => {write!(f, "Shader module {} can't be reflected", key)}
+			Self::LayoutMismatch(err) => {write!(f, "{}", err)}
+			Self::Validation(err) => {write!(f, "Validation failed: {}", err)}
+		}
+	}
+}
+
+impl<'a> error::Error for AssetError<'a> {}
+
+
+/// Compilation errors of a shader module with source lines they point at
+#[derive(Debug)]
+pub struct ShaderCompilationError {
+	pub shader: Box<str>,
+	pub messages: Vec<ShaderMessage>
+}
+
+#[derive(Debug)]
+pub struct ShaderMessage {
+	pub message: String,
+	pub location: Option<ShaderSpan>
+}
+
+/// Location in original shader file, line and column are 1-based
+#[derive(Debug)]
+pub struct ShaderSpan {
+	pub file: Box<str>,
+	pub line: u32,
+	pub column: u32,
+	pub length: u32,
+	pub line_text: String
+}
+
+impl ShaderCompilationError {
+	/// `origin` maps compiled source line to original file and line.
+	/// `None` if there are no error messages
+	pub(crate) fn new<'s>(
+		shader: &str,
+		source: Option<&str>,
+		origin: impl Fn(u32) -> Option<(&'s str, u32)>,
+		info: CompilationInfo
+	) -> Option<Self> {
+		let messages = info.messages.into_iter()
+			.filter(|message| message.message_type == CompilationMessageType::Error)
+			.map(|message| {
+				let location = message.location.zip(source).and_then(|(location, source)| {
+					let line_text = source.lines().nth(location.line_number.checked_sub(1)? as usize)?;
+					let (file, line) = origin(location.line_number)?;
+					Some(ShaderSpan {
+						file: file.into(),
+						line,
+						column: location.line_position,
+						length: location.length,
+						line_text: line_text.into()
+					})
+				});
+				// wgpu message contains its own rendering of the span after the first line
+				let text = message.message.trim();
+				let text = match location {
+					Some(_) => text.lines().next().unwrap_or_default(),
+					None => text
+				};
+				ShaderMessage { message: text.into(), location }
+			})
+			.collect::<Vec<_>>();
+		(!messages.is_empty()).then(|| Self { shader: shader.into(), messages })
+	}
+}
+
+impl fmt::Display for ShaderCompilationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Failed to compile shader {}", self.shader)?;
+		for message in &self.messages {
+			match &message.location {
+				Some(span) => {
+					write!(f, "\n{}:{}:{}: {}", span.file, span.line, span.column, message.message)?;
+					let indent = span.column.saturating_sub(1) as usize;
+					let underline = span.length.max(1) as usize;
+					write!(f, "\n    {}\n    {}{}", span.line_text, " ".repeat(indent), "^".repeat(underline))?;
+				}
+				None => write!(f, "\n{}: {}", self.shader, message.message)?
+			}
+		}
+		Ok(())
+	}
+}
+
+impl error::Error for ShaderCompilationError {}
+
+
+#[derive(Debug)]
+pub struct AssetDiagnostic {
+	pub asset: AssetId,
+	pub error: AssetError<'static>
+}
+
+/// All asset creation failures
+#[derive(Debug, Default)]
+pub struct AssetErrors(pub Vec<AssetDiagnostic>);
+
+impl AssetErrors {
+	pub(super) fn push(&mut self, asset: AssetId, error: AssetError) {
+		self.0.push(AssetDiagnostic { asset, error: error.into_owned() });
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl fmt::Display for AssetErrors {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Failed to create {} render asset(s)", self.0.len())?;
+		for diagnostic in &self.0 {
+			write!(f, "\n{}: {}", diagnostic.asset, diagnostic.error)?;
+		}
+		Ok(())
+	}
+}
+
+impl error::Error for AssetErrors {}
@@ -0,0 +1,575 @@
+use core::{error, fmt, num::NonZero};
+use std::{collections::BTreeMap, fs, io, path::{Path, PathBuf}};
+
+use serde::Deserialize;
+use wgpu::{
+	BindGroupLayoutEntry, BindingType, BlendState, BufferAddress, ColorWrites, DepthStencilState,
+	MultisampleState, PrimitiveState, PushConstantRange, ShaderStages, TextureFormat,
+	VertexAttribute, VertexStepMode
+};
+
+use super::{
+	desc::*, AssetErrors, AssetId, AssetKind, CreationMode, RenderAssetsCreation, ShaderDefines
+};
+
+
+/// Manifest compiled into the binary, located at `assets/render_assets.ron`
+const EMBEDDED_MANIFEST: &str = include_str!("../../../../assets/render_assets.ron");
+
+/// Render assets described by key, created in dependency order:
+/// shader modules, bind group layouts, pipeline layouts, then pipelines
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetManifest {
+	bind_group_layouts: BTreeMap<String, BindGroupLayoutEntryDesc>,
+	pipeline_layouts: BTreeMap<String, PipelineLayoutEntry>,
+	shader_modules: BTreeMap<String, ShaderModuleEntry>,
+	compute_pipelines: BTreeMap<String, ComputePipelineEntry>,
+	render_pipelines: BTreeMap<String, RenderPipelineEntry>
+}
+
+impl AssetManifest {
+	pub fn parse(source: &str) -> Result<Self, ManifestError> {
+		let manifest = ron::from_str::<Self>(source).map_err(ManifestError::Parse)?;
+		manifest.validate()?;
+		Ok(manifest)
+	}
+
+	pub fn embedded() -> Result<Self, ManifestError> {
+		Self::parse(EMBEDDED_MANIFEST)
+	}
+
+	pub fn load(path: &Path) -> Result<Self, ManifestError> {
+		let source = fs::read_to_string(path).map_err(|error| ManifestError::Read {
+			path: path.to_owned(),
+			error
+		})?;
+		Self::parse(&source)
+	}
+
+	/// Loads manifest file if given, embedded manifest otherwise
+	pub(crate) fn load_or_embedded(path: Option<&Path>) -> Result<Self, ManifestError> {
+		match path {
+			Some(path) => Self::load(path),
+			None => Self::embedded()
+		}
+	}
+
+	/// Rejects entries that deserialize but would be ignored
+	fn validate(&self) -> Result<(), ManifestError> {
+		let unused_defines = self.shader_modules.iter()
+			.find(|(_, module)| {
+				matches!(module.source, ShaderModuleSource::Wgsl(_)) && !module.defines.is_empty()
+			});
+		match unused_defines {
+			Some((key, _)) => Err(ManifestError::UnusedDefines(key.clone())),
+			None => Ok(())
+		}
+	}
+
+	/// Entries that failed are reported by asset kind and key
+	pub fn create(&self, ctx: &mut RenderAssetsCreation, mode: CreationMode) -> Result<(), AssetErrors> {
+		let mut errors = AssetErrors::default();
+		macro_rules! check {
+			($kind:ident, $key:expr, $result:expr) => {
+				if let Err(err) = $result {
+					errors.push(AssetId::new(AssetKind::$kind, $key), err);
+					if mode == CreationMode::FailFast {
+						return Err(errors);
+					}
+				}
+			};
+		}
+
+		for (key, module) in &self.shader_modules {
+			check!(ShaderModule, key, match &module.source {
+				ShaderModuleSource::File(path) => ctx.create(
+					ShaderFile::new(key, path).with_defines(module.defines.clone())
+				),
+				ShaderModuleSource::Wgsl(source) => ctx.create(ShaderModule::new(
+					key, ShaderSource::Wgsl(source.into())
+				))
+			});
+		}
+		for (key, layout) in &self.bind_group_layouts {
+			match layout {
+				BindGroupLayoutEntryDesc::Explicit(bindings) => {
+					let entries = bindings.iter()
+						.map(LayoutBinding::entry)
+						.collect::<Vec<_>>();
+					check!(BindGroupLayout, key, ctx.create(BindGroupLayout::new(key, &entries)));
+				}
+				BindGroupLayoutEntryDesc::Reflect { shaders, group } => {
+					let shaders = shaders.iter()
+						.map(String::as_str)
+						.collect::<Vec<_>>();
+					check!(BindGroupLayout, key, ctx.create(ReflectedBindGroupLayout {
+						key,
+						shaders: &shaders,
+						group: *group
+					}));
+				}
+			}
+		}
+		for (key, layout) in &self.pipeline_layouts {
+			let (bind_group_layouts, push_constant_ranges) = match layout {
+				PipelineLayoutEntry::Explicit { bind_group_layouts, push_constant_ranges } => {
+					(bind_group_layouts.clone(), push_constant_ranges.as_slice())
+				}
+				// Bind group layout of each group is created as `{key}@{group}`
+				PipelineLayoutEntry::Reflect { shaders, push_constant_ranges } => {
+					let shaders = shaders.iter()
+						.map(String::as_str)
+						.collect::<Vec<_>>();
+					let group_count = shaders.iter()
+						.filter_map(|shader| ctx.shader_reflection(shader))
+						.map(|reflection| reflection.group_count())
+						.max()
+						.unwrap_or(0);
+					let bind_group_layouts = (0..group_count)
+						.map(|group| format!("{}@{}", key, group))
+						.collect::<Vec<_>>();
+					for (group, layout) in (0..).zip(&bind_group_layouts) {
+						check!(BindGroupLayout, layout, ctx.create(ReflectedBindGroupLayout {
+							key: layout,
+							shaders: &shaders,
+							group
+						}));
+					}
+					(bind_group_layouts, push_constant_ranges.as_slice())
+				}
+			};
+			let bind_group_layouts = bind_group_layouts.iter()
+				.map(String::as_str)
+				.collect::<Vec<_>>();
+			check!(PipelineLayout, key, ctx.create(PipelineLayout {
+				key,
+				bind_group_layouts: &bind_group_layouts,
+				push_constant_ranges
+			}));
+		}
+		for (key, pipeline) in &self.compute_pipelines {
+			let constants = constants(&pipeline.constants);
+			check!(ComputePipeline, key, ctx.create_pipeline(ComputePipeline {
+				key,
+				layout: pipeline.layout.as_deref(),
+				module: &pipeline.module,
+				entry_point: pipeline.entry_point.as_deref(),
+				constants: &constants,
+				cache: None
+			}));
+		}
+		for (key, pipeline) in &self.render_pipelines {
+			let vertex = &pipeline.vertex;
+			let vertex_constants = constants(&vertex.constants);
+			let vertex_attributes = vertex.buffers.iter()
+				.map(|buffer| buffer.attributes.as_slice());
+			let vertex_buffers = vertex.buffers.iter()
+				.zip(vertex_attributes)
+				.map(|(buffer, attributes)| VertexBufferLayout {
+					array_stride: buffer.array_stride,
+					step_mode: buffer.step_mode,
+					attributes
+				})
+				.collect::<Vec<_>>();
+			let fragment_constants = pipeline.fragment.as_ref()
+				.map(|fragment| constants(&fragment.constants))
+				.unwrap_or_default();
+			let fragment_targets = pipeline.fragment.as_ref()
+				.map(|fragment| fragment.targets.iter()
+					.map(|target| target.as_ref().map(ColorTargetEntry::target))
+					.collect::<Vec<_>>())
+				.unwrap_or_default();
+
+			check!(RenderPipeline, key, ctx.create_pipeline(RenderPipeline {
+				key,
+				layout: pipeline.layout.as_deref(),
+				vertex: VertexStage {
+					module: &vertex.module,
+					entry_point: vertex.entry_point.as_deref(),
+					constants: &vertex_constants,
+					buffers: &vertex_buffers
+				},
+				fragment: pipeline.fragment.as_ref().map(|fragment| FragmentStage {
+					module: &fragment.module,
+					entry_point: fragment.entry_point.as_deref(),
+					constants: &fragment_constants,
+					targets: &fragment_targets
+				}),
+				primitive: pipeline.primitive,
+				depth_stencil: pipeline.depth_stencil.clone(),
+				multisample: pipeline.multisample,
+				cache: None
+			}));
+		}
+
+		if errors.is_empty() { Ok(()) } else { Err(errors) }
+	}
+}
+
+
+/// Override constants in the form of pipeline descs
+fn constants(constants: &BTreeMap<String, f64>) -> Vec<(&str, f64)> {
+	constants.iter()
+		.map(|(name, &value)| (name.as_str(), value))
+		.collect()
+}
+
+
+#[derive(Deserialize)]
+enum BindGroupLayoutEntryDesc {
+	Explicit(Vec<LayoutBinding>),
+	/// Bindings of the group declared by shader modules
+	Reflect {
+		shaders: Vec<String>,
+		group: u32
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutBinding {
+	binding: u32,
+	visibility: Vec<ShaderStage>,
+	ty: BindingType,
+	#[serde(default)]
+	count: Option<NonZero<u32>>
+}
+
+impl LayoutBinding {
+	fn entry(&self) -> BindGroupLayoutEntry {
+		BindGroupLayoutEntry {
+			binding: self.binding,
+			visibility: self.visibility.iter()
+				.fold(ShaderStages::NONE, |stages, stage| stages | stage.stages()),
+			ty: self.ty,
+			count: self.count
+		}
+	}
+}
+
+#[derive(Clone, Copy, Deserialize)]
+enum ShaderStage {
+	Vertex,
+	Fragment,
+	Compute
+}
+
+impl ShaderStage {
+	fn stages(self) -> ShaderStages {
+		match self {
+			Self::Vertex => ShaderStages::VERTEX,
+			Self::Fragment => ShaderStages::FRAGMENT,
+			Self::Compute => ShaderStages::COMPUTE
+		}
+	}
+}
+
+#[derive(Deserialize)]
+enum PipelineLayoutEntry {
+	Explicit {
+		bind_group_layouts: Vec<String>,
+		#[serde(default)]
+		push_constant_ranges: Vec<PushConstantRange>
+	},
+	/// Bind group layouts reflected from all groups declared by shader modules
+	Reflect {
+		shaders: Vec<String>,
+		#[serde(default)]
+		push_constant_ranges: Vec<PushConstantRange>
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShaderModuleEntry {
+	source: ShaderModuleSource,
+	/// Only file sources are preprocessed, defines of WGSL sources are rejected
+	#[serde(default)]
+	defines: ShaderDefines
+}
+
+#[derive(Deserialize)]
+enum ShaderModuleSource {
+	/// Path relative to `assets/shaders`
+	File(String),
+	Wgsl(String)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ComputePipelineEntry {
+	#[serde(default)]
+	layout: Option<String>,
+	module: String,
+	#[serde(default)]
+	entry_point: Option<String>,
+	/// Values of `override` declarations by name or id
+	#[serde(default)]
+	constants: BTreeMap<String, f64>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenderPipelineEntry {
+	#[serde(default)]
+	layout: Option<String>,
+	vertex: VertexEntry,
+	#[serde(default)]
+	fragment: Option<FragmentEntry>,
+	#[serde(default)]
+	primitive: PrimitiveState,
+	#[serde(default)]
+	depth_stencil: Option<DepthStencilState>,
+	#[serde(default)]
+	multisample: MultisampleState
+}
+
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VertexEntry {
+	module: String,
+	#[serde(default)]
+	entry_point: Option<String>,
+	#[serde(default)]
+	constants: BTreeMap<String, f64>,
+	#[serde(default)]
+	buffers: Vec<VertexBufferEntry>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VertexBufferEntry {
+	array_stride: BufferAddress,
+	#[serde(default)]
+	step_mode: VertexStepMode,
+	attributes: Vec<VertexAttribute>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FragmentEntry {
+	module: String,
+	#[serde(default)]
+	entry_point: Option<String>,
+	#[serde(default)]
+	constants: BTreeMap<String, f64>,
+	/// Single final target if omitted
+	#[serde(default = "FragmentEntry::final_target")]
+	targets: Vec<Option<ColorTargetEntry>>
+}
+
+impl FragmentEntry {
+	fn final_target() -> Vec<Option<ColorTargetEntry>> {
+		vec![Some(ColorTargetEntry { format: None, blend: None, write_mask: ColorChannel::all() })]
+	}
+}
+
+/// Format of the final target if `format` is omitted
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorTargetEntry {
+	#[serde(default)]
+	format: Option<TextureFormat>,
+	#[serde(default)]
+	blend: Option<BlendState>,
+	/// All channels if omitted
+	#[serde(default = "ColorChannel::all")]
+	write_mask: Vec<ColorChannel>
+}
+
+impl ColorTargetEntry {
+	fn target(&self) -> ColorTarget {
+		ColorTarget {
+			format: self.format,
+			blend: self.blend,
+			write_mask: self.write_mask.iter()
+				.fold(ColorWrites::empty(), |mask, channel| mask | channel.writes())
+		}
+	}
+}
+
+#[derive(Clone, Copy, Deserialize)]
+enum ColorChannel {
+	Red,
+	Green,
+	Blue,
+	Alpha
+}
+
+impl ColorChannel {
+	fn all() -> Vec<Self> {
+		vec![Self::Red, Self::Green, Self::Blue, Self::Alpha]
+	}
+
+	fn writes(self) -> ColorWrites {
+		match self {
+			Self::Red => ColorWrites::RED,
+			Self::Green => ColorWrites::GREEN,
+			Self::Blue => ColorWrites::BLUE,
+			Self::Alpha => ColorWrites::ALPHA
+		}
+	}
+}
+
+
+#[derive(Debug)]
+pub enum ManifestError {
+	Read {
+		path: PathBuf,
+		error: io::Error
+	},
+	Parse(ron::error::SpannedError),
+	/// Shader module with WGSL source declares defines
+	UnusedDefines(String)
+}
+
+impl fmt::Display for ManifestError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Read { path, error } => {
+				write!(f, "Failed to read asset manifest {}: {}", path.display(), error)
+			}
+			Self::Parse(err) => write!(f, "Failed to parse asset manifest: {}", err),
+			Self::UnusedDefines(key) => write!(
+				f, "Shader module {} has defines, but only file sources are preprocessed", key
+			)
+		}
+	}
+}
+
+impl error::Error for ManifestError {}
+
+
+#[cfg(test)]
+mod tests {
+	use wgpu::{BlendComponent, BlendFactor, BlendOperation, VertexFormat};
+
+	use super::*;
+
+
+	fn parse_error(source: &str) -> ron::error::SpannedError {
+		match AssetManifest::parse(source) {
+			Err(ManifestError::Parse(err)) => err,
+			Err(err) => panic!("Unexpected error: {}", err),
+			Ok(_) => panic!("Manifest parsed")
+		}
+	}
+
+	#[test]
+	fn embedded_manifest_parses() {
+		let manifest = AssetManifest::embedded().unwrap();
+		assert!(manifest.render_pipelines.contains_key("blit"));
+		assert!(manifest.compute_pipelines.contains_key("main_pass"));
+	}
+
+	#[test]
+	fn syntax_error_location() {
+		let err = parse_error("(\n\tshader_modules: {\n\t\t\"a\": (source: File(\"a.wgsl\")\n\t},\n)");
+		assert_eq!((err.position.line, err.position.col), (4, 2));
+	}
+
+	#[test]
+	fn unknown_field_location() {
+		let err = parse_error("(\n\tshader_modules: {\n\t\t\"a\": (source: File(\"a.wgsl\"), define: {}),\n\t},\n)");
+		assert!(matches!(err.code, ron::Error::NoSuchStructField { .. }), "{}", err);
+		assert_eq!(err.position.line, 3);
+	}
+
+	#[test]
+	fn unknown_variant_location() {
+		let err = parse_error("(\n\tbind_group_layouts: {\n\t\t\"a\": Reflected(shaders: [], group: 0),\n\t},\n)");
+		assert!(matches!(err.code, ron::Error::NoSuchEnumVariant { .. }), "{}", err);
+		assert_eq!(err.position.line, 3);
+	}
+
+	#[test]
+	fn defines_of_wgsl_source_are_rejected() {
+		let source = "(shader_modules: {\"a\": (source: Wgsl(\"\"), defines: {\"A\": \"1\"})})";
+		assert!(matches!(AssetManifest::parse(source), Err(ManifestError::UnusedDefines(key)) if key == "a"));
+
+		let source = "(shader_modules: {\"a\": (source: File(\"a.wgsl\"), defines: {\"A\": \"1\"})})";
+		assert!(AssetManifest::parse(source).is_ok());
+	}
+
+	#[test]
+	fn missing_file_is_read_error() {
+		let path = Path::new("missing/render_assets.ron");
+		let err = AssetManifest::load(path).err().unwrap();
+		assert!(matches!(&err, ManifestError::Read { path: err_path, .. } if err_path == path));
+		assert!(err.to_string().contains("missing/render_assets.ron"));
+	}
+
+	#[test]
+	fn render_pipeline_targets_and_vertex_buffers() {
+		let source = r#"(render_pipelines: {"a": (
+			vertex: (module: "a", buffers: [
+				(array_stride: 8, attributes: [(format: float32x2, offset: 0, shaderLocation: 0)]),
+				(array_stride: 16, step_mode: instance, attributes: [
+					(format: float32x2, offset: 0, shaderLocation: 1),
+					(format: unorm8x4, offset: 8, shaderLocation: 2),
+				]),
+			]),
+			fragment: Some((module: "a", targets: [
+				Some((
+					format: Some("rgba16float"),
+					blend: Some((
+						color: (srcFactor: r#src-alpha, dstFactor: r#one-minus-src-alpha, operation: add),
+						alpha: (srcFactor: one, dstFactor: one, operation: max),
+					)),
+					write_mask: [Red, Alpha],
+				)),
+				None,
+				Some(()),
+			])),
+		)})"#;
+		let manifest = AssetManifest::parse(source).unwrap();
+		let pipeline = &manifest.render_pipelines["a"];
+
+		let buffers = &pipeline.vertex.buffers;
+		assert_eq!(buffers.len(), 2);
+		assert_eq!((buffers[0].array_stride, buffers[0].step_mode), (8, VertexStepMode::Vertex));
+		assert_eq!((buffers[1].array_stride, buffers[1].step_mode), (16, VertexStepMode::Instance));
+		assert_eq!(buffers[1].attributes, [
+			VertexAttribute { format: VertexFormat::Float32x2, offset: 0, shader_location: 1 },
+			VertexAttribute { format: VertexFormat::Unorm8x4, offset: 8, shader_location: 2 }
+		]);
+
+		let targets = pipeline.fragment.as_ref().unwrap().targets.iter()
+			.map(|target| target.as_ref().map(ColorTargetEntry::target))
+			.collect::<Vec<_>>();
+		let blend = BlendState {
+			color: BlendComponent {
+				src_factor: BlendFactor::SrcAlpha,
+				dst_factor: BlendFactor::OneMinusSrcAlpha,
+				operation: BlendOperation::Add
+			},
+			alpha: BlendComponent {
+				src_factor: BlendFactor::One,
+				dst_factor: BlendFactor::One,
+				operation: BlendOperation::Max
+			}
+		};
+		assert_eq!(targets, [
+			Some(ColorTarget::format(TextureFormat::Rgba16Float)
+				.with_blend(blend)
+				.with_write_mask(ColorWrites::RED | ColorWrites::ALPHA)),
+			None,
+			Some(ColorTarget::FINAL_TARGET)
+		]);
+	}
+
+	#[test]
+	fn fragment_targets_default_to_final_target() {
+		let source = r#"(render_pipelines: {"a": (
+			vertex: (module: "a"),
+			fragment: Some((module: "a")),
+		)})"#;
+		let manifest = AssetManifest::parse(source).unwrap();
+		let targets = manifest.render_pipelines["a"].fragment.as_ref().unwrap().targets.iter()
+			.map(|target| target.as_ref().map(ColorTargetEntry::target))
+			.collect::<Vec<_>>();
+		assert_eq!(targets, [Some(ColorTarget::FINAL_TARGET)]);
+	}
+}
@@ -1,5 +1,21 @@
 pub use assets::*;
+use compiler::*;
+pub use dependencies::*;
+pub use error::*;
+pub use manifest::*;
+pub(crate) use pipeline_cache::*;
+pub use preprocessor::*;
+pub use reflection::*;
+pub(crate) use shaders::*;
 
 pub mod desc;
 pub mod util;
 mod assets;
+mod compiler;
+mod dependencies;
+mod error;
+mod manifest;
+mod pipeline_cache;
+mod preprocessor;
+mod reflection;
+mod shaders;
@@ -0,0 +1,146 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use log::{info, warn};
+use wgpu::{AdapterInfo, Device, Features, PipelineCache, PipelineCacheDescriptor};
+
+
+/// First line of cache files, followed by adapter identity line and cache data
+const HEADER: &[u8] = b"starflow pipeline cache 1\n";
+
+/// Pipeline cache data stored in a file per adapter.
+/// Data written by another adapter or driver version is discarded
+pub(crate) struct PipelineCacheFile {
+	path: PathBuf,
+	identity: String
+}
+
+impl PipelineCacheFile {
+	/// Pipeline caches are supported only by some backends
+	pub fn new(directory: &Path, adapter: &AdapterInfo) -> Option<Self> {
+		let key = wgpu::util::pipeline_cache_key(adapter)?;
+		Some(Self {
+			path: directory.join(key),
+			identity: format!(
+				"{:?} {:04x}:{:04x} {} {} {}",
+				adapter.backend, adapter.vendor, adapter.device,
+				adapter.name, adapter.driver, adapter.driver_info
+			)
+		})
+	}
+
+	/// Cache is empty if the file is missing, unreadable or stale
+	pub fn load(&self, device: &Device) -> Option<PipelineCache> {
+		if !device.features().contains(Features::PIPELINE_CACHE) {
+			return None;
+		}
+		let data = match fs::read(&self.path) {
+			Ok(data) => self.cache_data(&data).map(<[u8]>::to_vec),
+			Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+			Err(err) => {
+				warn!("Failed to read pipeline cache {}: {}", self.path.display(), err);
+				None
+			}
+		};
+		info!("Pipeline cache {} {}", self.path.display(), match data {
+			Some(_) => "loaded",
+			None => "is empty"
+		});
+		// SAFETY: Data was returned by `PipelineCache::get_data` for an adapter
+		// with the same identity, wgpu validates it and falls back to empty cache
+		let cache = unsafe {
+			device.create_pipeline_cache(&PipelineCacheDescriptor {
+				label: Some("pipeline_cache"),
+				data: data.as_deref(),
+				fallback: true
+			})
+		};
+		Some(cache)
+	}
+
+	/// Data is written to a temporary file first so interrupted saves don't corrupt the cache
+	pub fn save(&self, cache: &PipelineCache) -> io::Result<()> {
+		let Some(data) = cache.get_data() else {
+			return Ok(());
+		};
+		if let Some(directory) = self.path.parent() {
+			fs::create_dir_all(directory)?;
+		}
+		let temporary = self.path.with_extension("tmp");
+		fs::write(&temporary, self.file_contents(&data))?;
+		fs::rename(&temporary, &self.path)
+	}
+
+	fn file_contents(&self, data: &[u8]) -> Vec<u8> {
+		let mut file = Vec::with_capacity(HEADER.len() + self.identity.len() + 1 + data.len());
+		file.extend_from_slice(HEADER);
+		file.extend_from_slice(self.identity.as_bytes());
+		file.push(b'\n');
+		file.extend_from_slice(data);
+		file
+	}
+
+	fn cache_data<'d>(&self, file: &'d [u8]) -> Option<&'d [u8]> {
+		let Some(file) = file.strip_prefix(HEADER) else {
+			warn!("Discarding pipeline cache {} with unknown format", self.path.display());
+			return None;
+		};
+		let Some(identity_end) = file.iter().position(|&byte| byte == b'\n') else {
+			warn!("Discarding truncated pipeline cache {}", self.path.display());
+			return None;
+		};
+		let (identity, data) = file.split_at(identity_end);
+		if identity != self.identity.as_bytes() {
+			info!(
+				"Discarding pipeline cache {} written by {}",
+				self.path.display(), String::from_utf8_lossy(identity)
+			);
+			return None;
+		}
+		Some(&data[1..])
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+
+	fn cache_file(identity: &str) -> PipelineCacheFile {
+		PipelineCacheFile { path: "pipeline_cache".into(), identity: identity.to_owned() }
+	}
+
+	#[test]
+	fn data_roundtrips() {
+		let file = cache_file("Vulkan 10de:2684 GPU driver 1.0");
+		let contents = file.file_contents(b"data\nwith newline");
+		assert_eq!(file.cache_data(&contents), Some(&b"data\nwith newline"[..]));
+		assert_eq!(file.cache_data(&file.file_contents(b"")), Some(&b""[..]));
+	}
+
+	#[test]
+	fn unknown_header_is_discarded() {
+		let file = cache_file("Vulkan");
+		let mut contents = file.file_contents(b"data");
+		contents[HEADER.len() - 2] = b'0';
+		assert_eq!(file.cache_data(&contents), None);
+		assert_eq!(file.cache_data(b""), None);
+	}
+
+	#[test]
+	fn other_identity_is_discarded() {
+		let contents = cache_file("Vulkan 10de:2684 GPU driver 1.0").file_contents(b"data");
+		assert_eq!(cache_file("Vulkan 10de:2684 GPU driver 1.1").cache_data(&contents), None);
+		// Identity must match exactly, not just as a prefix
+		assert_eq!(cache_file("Vulkan 10de:2684 GPU").cache_data(&contents), None);
+	}
+
+	#[test]
+	fn truncated_file_is_discarded() {
+		let file = cache_file("Vulkan");
+		let contents = file.file_contents(b"data");
+		assert_eq!(file.cache_data(&contents[..HEADER.len() + 3]), None);
+		assert_eq!(file.cache_data(&contents[..HEADER.len() - 1]), None);
+		assert_eq!(file.cache_data(HEADER), None);
+	}
+}
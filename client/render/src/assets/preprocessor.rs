@@ -0,0 +1,376 @@
+use core::{error, fmt};
+use std::{borrow::Cow, collections::BTreeMap, io};
+
+use super::ShaderSources;
+
+
+/// Names defined for preprocessing, empty value only marks name as defined
+pub type ShaderDefines = BTreeMap<String, String>;
+
+/// Shader file preprocessed with specific defines
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderVariant {
+	pub path: Box<str>,
+	pub defines: ShaderDefines
+}
+
+/// WGSL source with resolved directives:
+/// `#include "path"`, `#define NAME [value]`, `#ifdef NAME`, `#ifndef NAME`, `#else`, `#endif`.
+/// Each file is included once, include paths are relative to `assets/shaders`
+pub(crate) struct PreprocessedShader {
+	pub source: String,
+	/// Preprocessed file first, then included files
+	pub files: Vec<Box<str>>,
+	/// Index of original file and 1-based line for each output line
+	lines: Vec<(usize, u32)>
+}
+
+impl PreprocessedShader {
+	/// Original file and line of 1-based output `line`
+	pub fn origin(&self, line: u32) -> Option<(&str, u32)> {
+		let &(file, line) = self.lines.get(line.checked_sub(1)? as usize)?;
+		Some((&self.files[file], line))
+	}
+}
+
+pub(crate) fn preprocess(
+	shaders: &ShaderSources,
+	variant: &ShaderVariant
+) -> Result<PreprocessedShader, PreprocessError> {
+	preprocess_with(&|path| shaders.load(path), variant)
+}
+
+/// Files are loaded by `load`
+fn preprocess_with(
+	load: &dyn Fn(&str) -> io::Result<Cow<'static, str>>,
+	variant: &ShaderVariant
+) -> Result<PreprocessedShader, PreprocessError> {
+	let mut preprocessor = Preprocessor {
+		load,
+		defines: variant.defines.clone(),
+		include_stack: Vec::new(),
+		output: PreprocessedShader {
+			source: String::new(),
+			files: Vec::new(),
+			lines: Vec::new()
+		}
+	};
+	preprocessor.process_file(&variant.path, None)?;
+	Ok(preprocessor.output)
+}
+
+
+struct Preprocessor<'l> {
+	load: &'l dyn Fn(&str) -> io::Result<Cow<'static, str>>,
+	defines: ShaderDefines,
+	include_stack: Vec<Box<str>>,
+	output: PreprocessedShader
+}
+
+struct Condition {
+	active: bool,
+	parent_active: bool,
+	has_else: bool,
+	line: u32
+}
+
+impl Preprocessor<'_> {
+	/// `included_at` is the file and line of include directive
+	fn process_file(&mut self, path: &str, included_at: Option<(&str, u32)>) -> Result<(), PreprocessError> {
+		let error_at = |kind| {
+			let (file, line) = included_at.unwrap_or((path, 0));
+			PreprocessError { file: file.into(), line, kind }
+		};
+		if self.include_stack.iter().any(|file| &**file == path) {
+			return Err(error_at(PreprocessErrorKind::IncludeCycle(path.into())));
+		}
+		if self.output.files.iter().any(|file| &**file == path) {
+			return Ok(());
+		}
+		let source = (self.load)(path)
+			.map_err(|err| error_at(PreprocessErrorKind::Load(path.into(), err)))?;
+
+		let file_index = self.output.files.len();
+		self.output.files.push(path.into());
+		self.include_stack.push(path.into());
+
+		let mut conditions: Vec<Condition> = Vec::new();
+		for (line, text) in (1..).zip(source.lines()) {
+			let error = |kind| PreprocessError { file: path.into(), line, kind };
+			let active = conditions.last().is_none_or(|condition| condition.active);
+
+			let Some(directive) = text.trim_start().strip_prefix('#') else {
+				if active {
+					self.push_line(text, file_index, line);
+				}
+				continue;
+			};
+			let mut words = directive.split_whitespace();
+			let name = words.next().unwrap_or_default();
+			let argument = words.next();
+			match name {
+				"ifdef" | "ifndef" => {
+					let define = argument.ok_or_else(|| error(PreprocessErrorKind::MissingArgument))?;
+					let defined = self.defines.contains_key(define);
+					conditions.push(Condition {
+						active: active && defined == (name == "ifdef"),
+						parent_active: active,
+						has_else: false,
+						line
+					});
+				}
+				"else" => {
+					let condition = conditions.last_mut()
+						.filter(|condition| !condition.has_else)
+						.ok_or_else(|| error(PreprocessErrorKind::UnexpectedDirective(name.into())))?;
+					condition.active = condition.parent_active && !condition.active;
+					condition.has_else = true;
+				}
+				"endif" => {
+					conditions.pop()
+						.ok_or_else(|| error(PreprocessErrorKind::UnexpectedDirective(name.into())))?;
+				}
+				_ if !active => {}
+				"define" => {
+					let define = argument.ok_or_else(|| error(PreprocessErrorKind::MissingArgument))?;
+					let value = words.collect::<Vec<_>>().join(" ");
+					self.defines.insert(define.into(), value);
+				}
+				"include" => {
+					let include = argument
+						.and_then(|path| path.strip_prefix('"')?.strip_suffix('"'))
+						.ok_or_else(|| error(PreprocessErrorKind::MissingArgument))?;
+					self.process_file(include, Some((path, line)))?;
+				}
+				_ => return Err(error(PreprocessErrorKind::UnknownDirective(name.into())))
+			}
+		}
+
+		self.include_stack.pop();
+		match conditions.pop() {
+			Some(condition) => Err(PreprocessError {
+				file: path.into(),
+				line: condition.line,
+				kind: PreprocessErrorKind::UnterminatedCondition
+			}),
+			None => Ok(())
+		}
+	}
+
+	/// Identifiers with defined values are replaced
+	fn push_line(&mut self, text: &str, file: usize, line: u32) {
+		let source = &mut self.output.source;
+		let mut rest = text;
+		while let Some(start) = rest.find(is_identifier_start) {
+			source.push_str(&rest[..start]);
+			rest = &rest[start..];
+			let end = rest.find(|c: char| !is_identifier_char(c)).unwrap_or(rest.len());
+			let identifier = &rest[..end];
+			match self.defines.get(identifier).filter(|value| !value.is_empty()) {
+				Some(value) => source.push_str(value),
+				None => source.push_str(identifier)
+			}
+			rest = &rest[end..];
+		}
+		source.push_str(rest);
+		source.push('\n');
+		self.output.lines.push((file, line));
+	}
+}
+
+fn is_identifier_start(c: char) -> bool {
+	c.is_alphabetic() || c == '_'
+}
+
+fn is_identifier_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '_'
+}
+
+
+/// Points at the file and line of failed directive, line is 0 for the file itself
+#[derive(Debug)]
+pub struct PreprocessError {
+	pub file: Box<str>,
+	pub line: u32,
+	pub kind: PreprocessErrorKind
+}
+
+#[derive(Debug)]
+pub enum PreprocessErrorKind {
+	Load(Box<str>, io::Error),
+	IncludeCycle(Box<str>),
+	UnknownDirective(Box<str>),
+	/// `#else` or `#endif` without matching `#ifdef`
+	UnexpectedDirective(Box<str>),
+	MissingArgument,
+	UnterminatedCondition
+}
+
+impl fmt::Display for PreprocessError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.line {
+			0 => write!(f, "{}: {}", self.file, self.kind),
+			line => write!(f, "{}:{}: {}", self.file, line, self.kind)
+		}
+	}
+}
+
+impl fmt::Display for PreprocessErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Load(path, err) => write!(f, "Failed to load shader {}: {}", path, err),
+			Self::IncludeCycle(path) => write!(f, "Include cycle through {}", path),
+			Self::UnknownDirective(name) => write!(f, "Unknown directive #{}", name),
+			Self::UnexpectedDirective(name) => write!(f, "Unexpected #{}", name),
+			Self::MissingArgument => write!(f, "Missing directive argument"),
+			Self::UnterminatedCondition => write!(f, "Condition is not terminated with #endif")
+		}
+	}
+}
+
+impl error::Error for PreprocessError {}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+
+	/// Preprocesses `main.wgsl` of in-memory `files`
+	fn preprocess_files(
+		files: &[(&'static str, &'static str)],
+		defines: &[(&str, &str)]
+	) -> Result<PreprocessedShader, PreprocessError> {
+		let files = files.to_vec();
+		let load = move |path: &str| files.iter()
+			.find(|(file, _)| *file == path)
+			.map(|(_, source)| Cow::Borrowed(*source))
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Missing test file"));
+		let variant = ShaderVariant {
+			path: "main.wgsl".into(),
+			defines: defines.iter()
+				.map(|&(name, value)| (name.to_owned(), value.to_owned()))
+				.collect()
+		};
+		preprocess_with(&load, &variant)
+	}
+
+	fn lines(shader: &PreprocessedShader) -> Vec<&str> {
+		shader.source.lines().collect()
+	}
+
+	#[test]
+	fn files_are_included_once() {
+		let shader = preprocess_files(&[
+			("main.wgsl", "#include \"a.wgsl\"\n#include \"common.wgsl\"\nmain"),
+			("a.wgsl", "#include \"common.wgsl\"\na"),
+			("common.wgsl", "common")
+		], &[]).unwrap();
+		assert_eq!(lines(&shader), ["common", "a", "main"]);
+		assert_eq!(shader.files, ["main.wgsl".into(), "a.wgsl".into(), "common.wgsl".into()]);
+	}
+
+	#[test]
+	fn include_cycle_points_at_include() {
+		let err = preprocess_files(&[
+			("main.wgsl", "#include \"a.wgsl\""),
+			("a.wgsl", "\n#include \"b.wgsl\""),
+			("b.wgsl", "#include \"a.wgsl\"")
+		], &[]).err().unwrap();
+		assert!(matches!(&err.kind, PreprocessErrorKind::IncludeCycle(path) if &**path == "a.wgsl"));
+		assert_eq!((&*err.file, err.line), ("b.wgsl", 1));
+	}
+
+	#[test]
+	fn missing_include_points_at_include() {
+		let err = preprocess_files(&[("main.wgsl", "\n#include \"missing.wgsl\"")], &[]).err().unwrap();
+		assert!(matches!(&err.kind, PreprocessErrorKind::Load(path, _) if &**path == "missing.wgsl"));
+		assert_eq!((&*err.file, err.line), ("main.wgsl", 2));
+	}
+
+	#[test]
+	fn nested_conditions() {
+		let source = "\
+			#ifdef A\n\
+			a\n\
+				#ifndef B\n\
+				a_not_b\n\
+				#else\n\
+				a_b\n\
+				#endif\n\
+			#else\n\
+			not_a\n\
+				#ifdef B\n\
+				not_a_b\n\
+				#endif\n\
+			#endif";
+		let files = [("main.wgsl", source)];
+		assert_eq!(lines(&preprocess_files(&files, &[]).unwrap()), ["not_a"]);
+		assert_eq!(lines(&preprocess_files(&files, &[("B", "")]).unwrap()), ["not_a", "not_a_b"]);
+		assert_eq!(lines(&preprocess_files(&files, &[("A", "")]).unwrap()), ["a", "a_not_b"]);
+		assert_eq!(lines(&preprocess_files(&files, &[("A", ""), ("B", "")]).unwrap()), ["a", "a_b"]);
+	}
+
+	#[test]
+	fn directives_in_inactive_branches_are_skipped() {
+		let shader = preprocess_files(&[
+			("main.wgsl", "#ifdef A\n#include \"missing.wgsl\"\n#define X 1\n#endif\nX")
+		], &[]).unwrap();
+		assert_eq!(lines(&shader), ["X"]);
+	}
+
+	#[test]
+	fn unbalanced_conditions() {
+		let err = preprocess_files(&[("main.wgsl", "#ifdef A\n#else\n#else\n#endif")], &[]).err().unwrap();
+		assert!(matches!(&err.kind, PreprocessErrorKind::UnexpectedDirective(name) if &**name == "else"));
+		assert_eq!(err.line, 3);
+
+		let err = preprocess_files(&[("main.wgsl", "#endif")], &[]).err().unwrap();
+		assert!(matches!(&err.kind, PreprocessErrorKind::UnexpectedDirective(name) if &**name == "endif"));
+
+		let err = preprocess_files(&[("main.wgsl", "a\n#ifdef A\n#ifdef B\n#endif")], &[]).err().unwrap();
+		assert!(matches!(err.kind, PreprocessErrorKind::UnterminatedCondition));
+		assert_eq!(err.line, 2);
+	}
+
+	#[test]
+	fn defines_replace_whole_identifiers() {
+		let shader = preprocess_files(&[
+			("main.wgsl", "#define SIZE 4u\n#define FLAG\nSIZE * SIZE_2 + FLAG(COLOR)")
+		], &[("COLOR", "vec3(1.0)")]).unwrap();
+		assert_eq!(lines(&shader), ["4u * SIZE_2 + FLAG(vec3(1.0))"]);
+	}
+
+	#[test]
+	fn defines_of_includes_are_visible_to_includer() {
+		let shader = preprocess_files(&[
+			("main.wgsl", "#include \"config.wgsl\"\n#ifdef FAST\nfast\n#endif\nN"),
+			("config.wgsl", "#define FAST\n#define N 8")
+		], &[]).unwrap();
+		assert_eq!(lines(&shader), ["fast", "8"]);
+	}
+
+	#[test]
+	fn unknown_directive_is_error() {
+		let err = preprocess_files(&[("main.wgsl", "a\n  #pragma once")], &[]).err().unwrap();
+		assert!(matches!(&err.kind, PreprocessErrorKind::UnknownDirective(name) if &**name == "pragma"));
+		assert_eq!((&*err.file, err.line), ("main.wgsl", 2));
+
+		let err = preprocess_files(&[("main.wgsl", "#ifdef")], &[]).err().unwrap();
+		assert!(matches!(err.kind, PreprocessErrorKind::MissingArgument));
+	}
+
+	#[test]
+	fn lines_map_to_original_files() {
+		let shader = preprocess_files(&[
+			("main.wgsl", "#include \"a.wgsl\"\n#ifdef A\nskipped\n#endif\nmain"),
+			("a.wgsl", "// a\n\na")
+		], &[]).unwrap();
+		assert_eq!(lines(&shader), ["// a", "", "a", "main"]);
+		assert_eq!(shader.origin(1), Some(("a.wgsl", 1)));
+		assert_eq!(shader.origin(3), Some(("a.wgsl", 3)));
+		assert_eq!(shader.origin(4), Some(("main.wgsl", 5)));
+		assert_eq!(shader.origin(0), None);
+		assert_eq!(shader.origin(5), None);
+	}
+}
@@ -0,0 +1,466 @@
+use core::{error, fmt};
+use std::collections::BTreeMap;
+
+use naga::{
+	front::wgsl, valid::{Capabilities, ValidationFlags, Validator}, AddressSpace, ArraySize,
+	Expression, ImageClass, ImageDimension, Literal, Module, ScalarKind, StorageAccess,
+	StorageFormat, TypeInner
+};
+use wgpu::{
+	BindGroupLayoutEntry, BindingType, BufferBindingType, SamplerBindingType, ShaderStages,
+	StorageTextureAccess, TextureFormat, TextureSampleType, TextureViewDimension
+};
+
+
+/// Resource bindings and entry points declared by a WGSL shader
+#[derive(Debug, Default)]
+pub struct ShaderReflection {
+	/// Bindings by group and binding index
+	pub bindings: BTreeMap<(u32, u32), ReflectedBinding>,
+	pub entry_points: Vec<ReflectedEntryPoint>
+}
+
+#[derive(Debug)]
+pub struct ReflectedBinding {
+	pub name: Option<String>,
+	/// Error if binding can't be described by a layout entry
+	pub ty: Result<BindingType, Box<str>>,
+	pub count: Option<core::num::NonZero<u32>>,
+	/// Stages of entry points using the binding
+	pub visibility: ShaderStages
+}
+
+#[derive(Debug)]
+pub struct ReflectedEntryPoint {
+	pub name: String,
+	pub stage: ShaderStages,
+	/// Size with default values of overrides, 0 if an override has no default
+	pub workgroup_size: [u32; 3],
+	/// Overrides setting the size, by dimension
+	pub workgroup_size_overrides: [Option<ReflectedOverride>; 3]
+}
+
+/// Pipeline-overridable constant, set by name or by id if it has one
+#[derive(Clone, Debug)]
+pub struct ReflectedOverride {
+	pub name: Option<String>,
+	pub id: Option<u16>
+}
+
+impl ReflectedEntryPoint {
+	/// Workgroup size of a pipeline created with override `constants`
+	pub fn specialized_workgroup_size(&self, constants: &[(&str, f64)]) -> [u32; 3] {
+		let mut size = self.workgroup_size;
+		for (size, reflected) in size.iter_mut().zip(&self.workgroup_size_overrides) {
+			let value = reflected.as_ref().and_then(|reflected| constants.iter()
+				.find(|&&(key, _)| reflected.is_set_by(key))
+				.map(|&(_, value)| value));
+			if let Some(value) = value {
+				*size = value as u32;
+			}
+		}
+		size
+	}
+}
+
+impl ReflectedOverride {
+	fn is_set_by(&self, key: &str) -> bool {
+		self.name.as_deref() == Some(key)
+			|| self.id.is_some_and(|id| key.parse() == Ok(id))
+	}
+}
+
+impl ShaderReflection {
+	/// Source is expected to be valid, errors are reported by shader compilation
+	pub fn from_wgsl(source: &str) -> Option<Self> {
+		let module = wgsl::parse_str(source).ok()?;
+		let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+			.validate(&module)
+			.ok()?;
+
+		let mut reflection = Self::default();
+		for (index, entry_point) in module.entry_points.iter().enumerate() {
+			let stage = map_stage(entry_point.stage);
+			let (workgroup_size, workgroup_size_overrides) =
+				reflect_workgroup_size(&module, entry_point);
+			reflection.entry_points.push(ReflectedEntryPoint {
+				name: entry_point.name.clone(),
+				stage,
+				workgroup_size,
+				workgroup_size_overrides
+			});
+			let function = info.get_entry_point(index);
+			for (handle, global) in module.global_variables.iter() {
+				let Some(binding) = &global.binding else { continue };
+				if function[handle].is_empty() {
+					continue;
+				}
+				reflection.bindings
+					.entry((binding.group, binding.binding))
+					.or_insert_with(|| {
+						let (ty, count) = reflect_binding(&module, global.space, global.ty);
+						ReflectedBinding { name: global.name.clone(), ty, count, visibility: ShaderStages::NONE }
+					})
+					.visibility |= stage;
+			}
+		}
+		Some(reflection)
+	}
+
+	/// Entry point of the stage with given name, or the only one of the stage if `name` is `None`
+	pub fn entry_point(
+		&self,
+		stage: ShaderStages,
+		name: Option<&str>
+	) -> Option<&ReflectedEntryPoint> {
+		let mut entry_points = self.entry_points.iter()
+			.filter(|entry_point| entry_point.stage == stage)
+			.filter(|entry_point| name.is_none_or(|name| entry_point.name == name));
+		entry_points.next().filter(|_| entry_points.next().is_none())
+	}
+
+	/// Bindings of the group used by `stages`
+	pub fn group(&self, group: u32, stages: ShaderStages) -> impl Iterator<Item = (u32, &ReflectedBinding)> {
+		self.bindings
+			.range((group, 0)..=(group, u32::MAX))
+			.filter(move |(_, binding)| binding.visibility.intersects(stages))
+			.map(|(&(_, binding), reflected)| (binding, reflected))
+	}
+
+	pub fn group_count(&self) -> u32 {
+		self.bindings.keys()
+			.next_back()
+			.map_or(0, |&(group, _)| group + 1)
+	}
+}
+
+fn map_stage(stage: naga::ShaderStage) -> ShaderStages {
+	match stage {
+		naga::ShaderStage::Vertex => ShaderStages::VERTEX,
+		naga::ShaderStage::Fragment => ShaderStages::FRAGMENT,
+		naga::ShaderStage::Compute => ShaderStages::COMPUTE,
+		naga::ShaderStage::Task => ShaderStages::TASK,
+		naga::ShaderStage::Mesh => ShaderStages::MESH
+	}
+}
+
+/// Only overrides used directly as size are resolved
+fn reflect_workgroup_size(
+	module: &Module,
+	entry_point: &naga::EntryPoint
+) -> ([u32; 3], [Option<ReflectedOverride>; 3]) {
+	let mut size = entry_point.workgroup_size;
+	let mut overrides = [None, None, None];
+	let Some(size_overrides) = &entry_point.workgroup_size_overrides else {
+		return (size, overrides);
+	};
+	for (axis, expression) in size_overrides.iter().enumerate() {
+		let Some(&expression) = expression.as_ref() else { continue };
+		let Expression::Override(handle) = module.global_expressions[expression] else {
+			size[axis] = 0;
+			continue;
+		};
+		let reflected = &module.overrides[handle];
+		size[axis] = reflected.init
+			.and_then(|init| match module.global_expressions[init] {
+				Expression::Literal(Literal::U32(value)) => Some(value),
+				Expression::Literal(Literal::I32(value)) => u32::try_from(value).ok(),
+				Expression::Literal(Literal::AbstractInt(value)) => u32::try_from(value).ok(),
+				_ => None
+			})
+			.unwrap_or(0);
+		overrides[axis] = Some(ReflectedOverride { name: reflected.name.clone(), id: reflected.id });
+	}
+	(size, overrides)
+}
+
+fn reflect_binding(
+	module: &Module,
+	space: AddressSpace,
+	ty: naga::Handle<naga::Type>
+) -> (Result<BindingType, Box<str>>, Option<core::num::NonZero<u32>>) {
+	let (inner, count) = match &module.types[ty].inner {
+		TypeInner::BindingArray { base, size } => {
+			let count = match size {
+				ArraySize::Constant(size) => Some(*size),
+				_ => return (Err("Binding array size must be constant".into()), None)
+			};
+			(&module.types[*base].inner, count)
+		}
+		inner => (inner, None)
+	};
+
+	let ty = match (space, inner) {
+		(AddressSpace::Uniform, _) => Ok(BindingType::Buffer {
+			ty: BufferBindingType::Uniform,
+			has_dynamic_offset: false,
+			min_binding_size: None
+		}),
+		(AddressSpace::Storage { access }, _) => Ok(BindingType::Buffer {
+			ty: BufferBindingType::Storage { read_only: !access.contains(StorageAccess::STORE) },
+			has_dynamic_offset: false,
+			min_binding_size: None
+		}),
+		(AddressSpace::Handle, TypeInner::Sampler { comparison }) => Ok(BindingType::Sampler(
+			if *comparison { SamplerBindingType::Comparison } else { SamplerBindingType::Filtering }
+		)),
+		(AddressSpace::Handle, TypeInner::Image { dim, arrayed, class }) => {
+			let view_dimension = map_view_dimension(*dim, *arrayed);
+			match *class {
+				ImageClass::Sampled { kind, multi } => Ok(BindingType::Texture {
+					sample_type: match kind {
+						ScalarKind::Sint => TextureSampleType::Sint,
+						ScalarKind::Uint => TextureSampleType::Uint,
+						_ => TextureSampleType::Float { filterable: !multi }
+					},
+					view_dimension,
+					multisampled: multi
+				}),
+				ImageClass::Depth { multi } => Ok(BindingType::Texture {
+					sample_type: TextureSampleType::Depth,
+					view_dimension,
+					multisampled: multi
+				}),
+				ImageClass::Storage { format, access } => match map_storage_format(format) {
+					Some(format) => Ok(BindingType::StorageTexture {
+						access: map_storage_access(access),
+						format,
+						view_dimension
+					}),
+					None => Err(format!("Unsupported storage texture format {:?}", format).into())
+				}
+			}
+		}
+		(_, inner) => Err(format!("Unsupported binding type {:?}", inner).into())
+	};
+	(ty, count)
+}
+
+fn map_view_dimension(dim: ImageDimension, arrayed: bool) -> TextureViewDimension {
+	match (dim, arrayed) {
+		(ImageDimension::D1, _) => TextureViewDimension::D1,
+		(ImageDimension::D2, false) => TextureViewDimension::D2,
+		(ImageDimension::D2, true) => TextureViewDimension::D2Array,
+		(ImageDimension::D3, _) => TextureViewDimension::D3,
+		(ImageDimension::Cube, false) => TextureViewDimension::Cube,
+		(ImageDimension::Cube, true) => TextureViewDimension::CubeArray
+	}
+}
+
+fn map_storage_access(access: StorageAccess) -> StorageTextureAccess {
+	let load = access.contains(StorageAccess::LOAD);
+	let store = access.contains(StorageAccess::STORE);
+	match (load, store) {
+		(true, true) => StorageTextureAccess::ReadWrite,
+		(true, false) => StorageTextureAccess::ReadOnly,
+		_ => StorageTextureAccess::WriteOnly
+	}
+}
+
+fn map_storage_format(format: StorageFormat) -> Option<TextureFormat> {
+	use StorageFormat as S;
+	use TextureFormat as T;
+
+	Some(match format {
+		S::R8Unorm => T::R8Unorm,
+		S::R8Snorm => T::R8Snorm,
+		S::R8Uint => T::R8Uint,
+		S::R8Sint => T::R8Sint,
+		S::R16Uint => T::R16Uint,
+		S::R16Sint => T::R16Sint,
+		S::R16Float => T::R16Float,
+		S::Rg8Unorm => T::Rg8Unorm,
+		S::Rg8Snorm => T::Rg8Snorm,
+		S::Rg8Uint => T::Rg8Uint,
+		S::Rg8Sint => T::Rg8Sint,
+		S::R32Uint => T::R32Uint,
+		S::R32Sint => T::R32Sint,
+		S::R32Float => T::R32Float,
+		S::Rg16Uint => T::Rg16Uint,
+		S::Rg16Sint => T::Rg16Sint,
+		S::Rg16Float => T::Rg16Float,
+		S::Rgba8Unorm => T::Rgba8Unorm,
+		S::Rgba8Snorm => T::Rgba8Snorm,
+		S::Rgba8Uint => T::Rgba8Uint,
+		S::Rgba8Sint => T::Rgba8Sint,
+		S::Bgra8Unorm => T::Bgra8Unorm,
+		S::Rgb10a2Uint => T::Rgb10a2Uint,
+		S::Rgb10a2Unorm => T::Rgb10a2Unorm,
+		S::Rg11b10Ufloat => T::Rg11b10Ufloat,
+		S::R64Uint => T::R64Uint,
+		S::Rg32Uint => T::Rg32Uint,
+		S::Rg32Sint => T::Rg32Sint,
+		S::Rg32Float => T::Rg32Float,
+		S::Rgba16Uint => T::Rgba16Uint,
+		S::Rgba16Sint => T::Rgba16Sint,
+		S::Rgba16Float => T::Rgba16Float,
+		S::Rgba32Uint => T::Rgba32Uint,
+		S::Rgba32Sint => T::Rgba32Sint,
+		S::Rgba32Float => T::Rgba32Float,
+		S::R16Unorm => T::R16Unorm,
+		S::R16Snorm => T::R16Snorm,
+		S::Rg16Unorm => T::Rg16Unorm,
+		S::Rg16Snorm => T::Rg16Snorm,
+		S::Rgba16Unorm => T::Rgba16Unorm,
+		S::Rgba16Snorm => T::Rgba16Snorm
+	})
+}
+
+
+/// Layout entries of a group merged from all shaders, visible to stages using them
+pub(crate) fn reflect_group<'s>(
+	shaders: impl IntoIterator<Item = (&'s str, &'s ShaderReflection)>,
+	group: u32
+) -> Result<Vec<BindGroupLayoutEntry>, LayoutMismatch> {
+	let mut entries = Vec::<BindGroupLayoutEntry>::new();
+	for (shader, reflection) in shaders {
+		for (binding, reflected) in reflection.group(group, ShaderStages::all()) {
+			let mismatch = |kind| LayoutMismatch {
+				shader: shader.into(), group, binding, name: reflected.name.clone(), kind
+			};
+			let ty = *reflected.ty.as_ref()
+				.map_err(|reason| mismatch(LayoutMismatchKind::Unsupported(reason.clone())))?;
+			match entries.iter_mut().find(|entry| entry.binding == binding) {
+				Some(entry) if entry.ty == ty && entry.count == reflected.count => {
+					entry.visibility |= reflected.visibility;
+				}
+				Some(entry) => {
+					return Err(mismatch(LayoutMismatchKind::Conflict { first: entry.ty, second: ty }));
+				}
+				None => entries.push(BindGroupLayoutEntry {
+					binding,
+					visibility: reflected.visibility,
+					ty,
+					count: reflected.count
+				})
+			}
+		}
+	}
+	entries.sort_by_key(|entry| entry.binding);
+	Ok(entries)
+}
+
+/// Checks layout entries of a group against bindings the shader stages use
+pub(crate) fn validate_group(
+	shader: &str,
+	reflection: &ShaderReflection,
+	group: u32,
+	stages: ShaderStages,
+	entries: &[BindGroupLayoutEntry]
+) -> Result<(), LayoutMismatch> {
+	for (binding, reflected) in reflection.group(group, stages) {
+		let mismatch = |kind| LayoutMismatch {
+			shader: shader.into(), group, binding, name: reflected.name.clone(), kind
+		};
+		let ty = reflected.ty.as_ref()
+			.map_err(|reason| mismatch(LayoutMismatchKind::Unsupported(reason.clone())))?;
+		let entry = entries.iter()
+			.find(|entry| entry.binding == binding)
+			.ok_or_else(|| mismatch(LayoutMismatchKind::MissingEntry))?;
+		if !entry.visibility.contains(reflected.visibility & stages) {
+			return Err(mismatch(LayoutMismatchKind::Visibility {
+				layout: entry.visibility,
+				shader: reflected.visibility & stages
+			}));
+		}
+		if !is_compatible(&entry.ty, ty) || entry.count != reflected.count {
+			return Err(mismatch(LayoutMismatchKind::Type { layout: entry.ty, shader: *ty }));
+		}
+	}
+	Ok(())
+}
+
+/// Group is missing from the pipeline layout
+pub(crate) fn validate_missing_group(
+	shader: &str,
+	reflection: &ShaderReflection,
+	group: u32,
+	stages: ShaderStages
+) -> Result<(), LayoutMismatch> {
+	match reflection.group(group, stages).next() {
+		Some((binding, reflected)) => Err(LayoutMismatch {
+			shader: shader.into(),
+			group,
+			binding,
+			name: reflected.name.clone(),
+			kind: LayoutMismatchKind::MissingGroup
+		}),
+		None => Ok(())
+	}
+}
+
+/// Layout may allow more than the shader uses where wgpu allows it
+fn is_compatible(layout: &BindingType, shader: &BindingType) -> bool {
+	match (layout, shader) {
+		(
+			BindingType::Buffer { ty: layout, .. },
+			BindingType::Buffer { ty: shader, .. }
+		) => match (layout, shader) {
+			(BufferBindingType::Storage { read_only: false }, BufferBindingType::Storage { .. }) => true,
+			(layout, shader) => layout == shader
+		},
+		(
+			BindingType::Texture { sample_type: TextureSampleType::Float { .. }, view_dimension, multisampled },
+			BindingType::Texture { sample_type: TextureSampleType::Float { .. }, view_dimension: shader_dimension, multisampled: shader_multisampled }
+		) => view_dimension == shader_dimension && multisampled == shader_multisampled,
+		(BindingType::Sampler(_), BindingType::Sampler(SamplerBindingType::Filtering)) => {
+			!matches!(layout, BindingType::Sampler(SamplerBindingType::Comparison))
+		}
+		(layout, shader) => layout == shader
+	}
+}
+
+
+#[derive(Debug)]
+pub struct LayoutMismatch {
+	pub shader: Box<str>,
+	pub group: u32,
+	pub binding: u32,
+	pub name: Option<String>,
+	pub kind: LayoutMismatchKind
+}
+
+#[derive(Debug)]
+pub enum LayoutMismatchKind {
+	MissingGroup,
+	MissingEntry,
+	Visibility {
+		layout: ShaderStages,
+		shader: ShaderStages
+	},
+	Type {
+		layout: BindingType,
+		shader: BindingType
+	},
+	/// Bindings of different shaders conflict
+	Conflict {
+		first: BindingType,
+		second: BindingType
+	},
+	Unsupported(Box<str>)
+}
+
+impl fmt::Display for LayoutMismatch {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Shader {} @group({}) @binding({})", self.shader, self.group, self.binding)?;
+		if let Some(name) = &self.name {
+			write!(f, " {}", name)?;
+		}
+		match &self.kind {
+			LayoutMismatchKind::MissingGroup => write!(f, " has no bind group layout in pipeline layout"),
+			LayoutMismatchKind::MissingEntry => write!(f, " has no entry in bind group layout"),
+			LayoutMismatchKind::Visibility { layout, shader } => {
+				write!(f, " is used by {:?}, but visible only to {:?}", shader, layout)
+			}
+			LayoutMismatchKind::Type { layout, shader } => {
+				write!(f, " is {:?} in shader, but {:?} in layout", shader, layout)
+			}
+			LayoutMismatchKind::Conflict { first, second } => {
+				write!(f, " is declared as both {:?} and {:?}", first, second)
+			}
+			LayoutMismatchKind::Unsupported(reason) => write!(f, " can't be reflected: {}", reason)
+		}
+	}
+}
+
+impl error::Error for LayoutMismatch {}
@@ -0,0 +1,123 @@
+use std::{borrow::Cow, io};
+#[cfg(test)]
+use std::collections::HashMap;
+
+#[cfg(feature="hot-reload")]
+pub(crate) use hot_reload_features::*;
+
+
+/// Shader files compiled into the binary, paths are relative to `assets/shaders`
+const EMBEDDED_SHADERS: &[(&str, &str)] = &[
+	("main_pass.wgsl", include_str!("../../../../assets/shaders/main_pass.wgsl")),
+	("fullscreen.wgsl", include_str!("../../../../assets/shaders/fullscreen.wgsl")),
+	("fullscreen_output.wgsl", include_str!("../../../../assets/shaders/fullscreen_output.wgsl")),
+	("blit.wgsl", include_str!("../../../../assets/shaders/blit.wgsl"))
+];
+
+/// Where shader files are loaded from
+#[derive(Default)]
+pub(crate) enum ShaderSources {
+	#[default]
+	Embedded,
+	/// Dev mode, shaders are loaded from directory and reloaded on change
+	#[cfg(feature="hot-reload")]
+	Watched(ShaderWatcher),
+	/// Sources by path
+	#[cfg(test)]
+	Memory(HashMap<&'static str, &'static str>)
+}
+
+impl ShaderSources {
+	pub fn load(&self, path: &str) -> io::Result<Cow<'static, str>> {
+		match self {
+			Self::Embedded => EMBEDDED_SHADERS.iter()
+				.find(|(embedded, _)| *embedded == path)
+				.map(|(_, source)| Cow::Borrowed(*source))
+				.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Shader is not embedded")),
+			#[cfg(feature="hot-reload")]
+			Self::Watched(watcher) => watcher.load(path).map(Cow::Owned),
+			#[cfg(test)]
+			Self::Memory(files) => files.get(path)
+				.map(|source| Cow::Borrowed(*source))
+				.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Missing shader"))
+		}
+	}
+
+	/// Paths of all known shader files
+	#[allow(dead_code)]
+	pub fn paths() -> impl Iterator<Item = &'static str> {
+		EMBEDDED_SHADERS.iter().map(|(path, _)| *path)
+	}
+}
+
+
+#[cfg(feature="hot-reload")]
+mod hot_reload_features {
+	use std::{collections::HashSet, fs, io, path::{Path, PathBuf}, sync::mpsc};
+
+	use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+
+	pub(crate) struct ShaderWatcher {
+		directory: PathBuf,
+		/// Asset manifest file watched along with shaders
+		manifest: Option<PathBuf>,
+		events: mpsc::Receiver<notify::Result<Event>>,
+		_watcher: RecommendedWatcher
+	}
+
+	/// Files changed since last check
+	#[derive(Default)]
+	pub(crate) struct WatchedChanges {
+		/// Paths relative to watched directory
+		pub shaders: HashSet<Box<str>>,
+		pub manifest: bool
+	}
+
+	impl ShaderWatcher {
+		pub fn new(directory: &Path, manifest: Option<&Path>) -> notify::Result<Self> {
+			let directory = directory.canonicalize()?;
+			let manifest = manifest.map(Path::canonicalize).transpose()?;
+			let (sender, events) = mpsc::channel();
+			let mut watcher = notify::recommended_watcher(sender)?;
+			watcher.watch(&directory, RecursiveMode::Recursive)?;
+			// Parent is watched since editors may replace the file instead of modifying it
+			let manifest_directory = manifest.as_deref().and_then(Path::parent);
+			if let Some(parent) = manifest_directory.filter(|parent| !parent.starts_with(&directory)) {
+				watcher.watch(parent, RecursiveMode::NonRecursive)?;
+			}
+			Ok(Self { directory, manifest, events, _watcher: watcher })
+		}
+
+		pub fn load(&self, path: &str) -> io::Result<String> {
+			fs::read_to_string(self.directory.join(path))
+		}
+
+		/// Shader files and manifest changed since last call
+		pub fn changes(&self) -> WatchedChanges {
+			let paths = self.events.try_iter()
+				.filter_map(|event| event
+					.inspect_err(|err| log::warn!("Shader watcher error: {}", err))
+					.ok()
+				)
+				.filter(|event| event.kind.is_create() || event.kind.is_modify())
+				.flat_map(|event| event.paths);
+			let mut changes = WatchedChanges::default();
+			for path in paths {
+				if self.manifest.as_ref().is_some_and(|manifest| *manifest == path) {
+					changes.manifest = true;
+				}
+				else if let Some(path) = self.relative_path(&path) {
+					changes.shaders.insert(path);
+				}
+			}
+			changes
+		}
+
+		fn relative_path(&self, path: &Path) -> Option<Box<str>> {
+			let path = path.strip_prefix(&self.directory).ok()?;
+			let path = path.to_str()?.replace('\\', "/");
+			path.ends_with(".wgsl").then(|| path.into())
+		}
+	}
+}
@@ -1,16 +1,27 @@
 use std::num::NonZero;
 
-pub use wgpu::{TextureFormat, StorageTextureAccess};
-use wgpu::{BindGroupLayoutEntry, BindingType, ShaderStages, TextureViewDimension};
+pub use wgpu::{
+	SamplerBindingType, ShaderStages, StorageTextureAccess, TextureFormat, TextureSampleType
+};
+use wgpu::{BindGroupLayoutEntry, BindingType, BufferBindingType, TextureViewDimension};
 
+/// Builds layout entries, terminals are named after WGSL binding types
 pub struct BindGroupLayoutEntryBuilder {
 	binding: u32,
 	visibility: ShaderStages,
-	count: Option<NonZero<u32>>
+	count: Option<NonZero<u32>>,
+	has_dynamic_offset: bool,
+	min_binding_size: Option<NonZero<u64>>
 }
 
 pub fn binding(binding: u32) -> BindGroupLayoutEntryBuilder {
-	BindGroupLayoutEntryBuilder { binding, visibility: ShaderStages::NONE, count: None }
+	BindGroupLayoutEntryBuilder {
+		binding,
+		visibility: ShaderStages::NONE,
+		count: None,
+		has_dynamic_offset: false,
+		min_binding_size: None
+	}
 }
 
 #[allow(dead_code)]
@@ -21,21 +32,177 @@ impl BindGroupLayoutEntryBuilder {
 		self
 	}
 
+	/// Makes the entry a `binding_array` of `count` elements
 	pub fn count(mut self, count: NonZero<u32>) -> Self {
 		self.count = Some(count);
 		self
 	}
 
+	/// Used only by buffers
+	pub fn dynamic_offset(mut self) -> Self {
+		self.has_dynamic_offset = true;
+		self
+	}
+
+	/// Used only by buffers
+	pub fn min_binding_size(mut self, size: NonZero<u64>) -> Self {
+		self.min_binding_size = Some(size);
+		self
+	}
+
+	pub fn ty(self, ty: BindingType) -> BindGroupLayoutEntry {
+		BindGroupLayoutEntry {
+			binding: self.binding,
+			visibility: self.visibility,
+			ty,
+			count: self.count
+		}
+	}
+
+	// Buffers
+
+	pub fn uniform_buffer(self) -> BindGroupLayoutEntry {
+		self.buffer(BufferBindingType::Uniform)
+	}
+
+	pub fn storage_buffer(self) -> BindGroupLayoutEntry {
+		self.buffer(BufferBindingType::Storage { read_only: false })
+	}
+
+	pub fn storage_buffer_read_only(self) -> BindGroupLayoutEntry {
+		self.buffer(BufferBindingType::Storage { read_only: true })
+	}
+
+	fn buffer(self, ty: BufferBindingType) -> BindGroupLayoutEntry {
+		let ty = BindingType::Buffer {
+			ty,
+			has_dynamic_offset: self.has_dynamic_offset,
+			min_binding_size: self.min_binding_size
+		};
+		self.ty(ty)
+	}
+
+	// Sampled textures
+
+	pub fn texture_1d(self, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+		self.texture(sample_type, TextureViewDimension::D1, false)
+	}
+
+	pub fn texture_2d(self, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+		self.texture(sample_type, TextureViewDimension::D2, false)
+	}
+
+	pub fn texture_2d_array(self, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+		self.texture(sample_type, TextureViewDimension::D2Array, false)
+	}
+
+	pub fn texture_3d(self, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+		self.texture(sample_type, TextureViewDimension::D3, false)
+	}
+
+	pub fn texture_cube(self, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+		self.texture(sample_type, TextureViewDimension::Cube, false)
+	}
+
+	pub fn texture_cube_array(self, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+		self.texture(sample_type, TextureViewDimension::CubeArray, false)
+	}
+
+	/// Multisampled float textures can't be filtered
+	pub fn texture_multisampled_2d(self, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+		self.texture(sample_type, TextureViewDimension::D2, true)
+	}
+
+	// Depth textures
+
+	pub fn texture_depth_2d(self) -> BindGroupLayoutEntry {
+		self.texture(TextureSampleType::Depth, TextureViewDimension::D2, false)
+	}
+
+	pub fn texture_depth_2d_array(self) -> BindGroupLayoutEntry {
+		self.texture(TextureSampleType::Depth, TextureViewDimension::D2Array, false)
+	}
+
+	pub fn texture_depth_cube(self) -> BindGroupLayoutEntry {
+		self.texture(TextureSampleType::Depth, TextureViewDimension::Cube, false)
+	}
+
+	pub fn texture_depth_cube_array(self) -> BindGroupLayoutEntry {
+		self.texture(TextureSampleType::Depth, TextureViewDimension::CubeArray, false)
+	}
+
+	pub fn texture_depth_multisampled_2d(self) -> BindGroupLayoutEntry {
+		self.texture(TextureSampleType::Depth, TextureViewDimension::D2, true)
+	}
+
+	fn texture(
+		self,
+		sample_type: TextureSampleType,
+		view_dimension: TextureViewDimension,
+		multisampled: bool
+	) -> BindGroupLayoutEntry {
+		self.ty(BindingType::Texture { sample_type, view_dimension, multisampled })
+	}
+
+	// Storage textures
+
+	pub fn texture_storage_1d(
+		self,
+		format: TextureFormat,
+		access: StorageTextureAccess
+	) -> BindGroupLayoutEntry {
+		self.texture_storage(format, access, TextureViewDimension::D1)
+	}
+
 	pub fn texture_storage_2d(
 		self,
 		format: TextureFormat,
 		access: StorageTextureAccess
 	) -> BindGroupLayoutEntry {
-		BindGroupLayoutEntry {
-			binding: self.binding,
-			visibility: self.visibility, 
-			ty: BindingType::StorageTexture { access, format, view_dimension: TextureViewDimension::D2 },
-			count: self.count
-		}
+		self.texture_storage(format, access, TextureViewDimension::D2)
+	}
+
+	pub fn texture_storage_2d_array(
+		self,
+		format: TextureFormat,
+		access: StorageTextureAccess
+	) -> BindGroupLayoutEntry {
+		self.texture_storage(format, access, TextureViewDimension::D2Array)
+	}
+
+	pub fn texture_storage_3d(
+		self,
+		format: TextureFormat,
+		access: StorageTextureAccess
+	) -> BindGroupLayoutEntry {
+		self.texture_storage(format, access, TextureViewDimension::D3)
+	}
+
+	fn texture_storage(
+		self,
+		format: TextureFormat,
+		access: StorageTextureAccess,
+		view_dimension: TextureViewDimension
+	) -> BindGroupLayoutEntry {
+		self.ty(BindingType::StorageTexture { access, format, view_dimension })
+	}
+
+	// Samplers
+
+	pub fn sampler(self) -> BindGroupLayoutEntry {
+		self.ty(BindingType::Sampler(SamplerBindingType::Filtering))
+	}
+
+	pub fn sampler_non_filtering(self) -> BindGroupLayoutEntry {
+		self.ty(BindingType::Sampler(SamplerBindingType::NonFiltering))
+	}
+
+	pub fn sampler_comparison(self) -> BindGroupLayoutEntry {
+		self.ty(BindingType::Sampler(SamplerBindingType::Comparison))
+	}
+
+	/// Requires `Features::EXPERIMENTAL_RAY_QUERY`
+	pub fn acceleration_structure(self) -> BindGroupLayoutEntry {
+		self.ty(BindingType::AccelerationStructure { vertex_return: false })
 	}
 }
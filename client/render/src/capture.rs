@@ -0,0 +1,205 @@
+use core::{error, fmt};
+use std::{fs::File, io::{self, BufReader, BufWriter}, path::Path};
+
+use wgpu::{BufferAsyncError, PollError, SurfaceError, TextureFormat};
+
+use starflow_util::Size;
+
+
+/// RGBA8 image read back from GPU texture
+pub struct CapturedFrame {
+	size: Size<u32>,
+	data: Vec<u8>
+}
+
+impl CapturedFrame {
+	/// `data` is expected to be tightly packed RGBA8 rows
+	pub fn new(size: Size<u32>, data: Vec<u8>) -> Self {
+		debug_assert_eq!(data.len(), (size.width * size.height * 4) as usize);
+		Self { size, data }
+	}
+
+	pub fn size(&self) -> Size<u32> {
+		self.size
+	}
+
+	pub fn data(&self) -> &[u8] {
+		&self.data
+	}
+
+	pub fn into_data(self) -> Vec<u8> {
+		self.data
+	}
+
+	pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+		let offset = ((y * self.size.width + x) * 4) as usize;
+		let mut pixel = [0; 4];
+		pixel.copy_from_slice(&self.data[offset..offset + 4]);
+		pixel
+	}
+
+	pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), CaptureError> {
+		let file = File::create(path)?;
+		let mut encoder = png::Encoder::new(
+			BufWriter::new(file),
+			self.size.width,
+			self.size.height
+		);
+		encoder.set_color(png::ColorType::Rgba);
+		encoder.set_depth(png::BitDepth::Eight);
+		encoder
+			.write_header()?
+			.write_image_data(&self.data)?;
+		Ok(())
+	}
+
+	/// Loads 8-bit RGBA png, e.g. previously saved with [`CapturedFrame::save_png`]
+	pub fn load_png(path: impl AsRef<Path>) -> Result<Self, CaptureError> {
+		let file = File::open(path)?;
+		let mut reader = png::Decoder::new(BufReader::new(file))
+			.read_info()?;
+		let mut data = vec![0; reader.output_buffer_size()];
+		let info = reader.next_frame(&mut data)?;
+		if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+			return Err(CaptureError::UnsupportedImage(info.color_type, info.bit_depth));
+		}
+		data.truncate(info.buffer_size());
+		Ok(Self::new(Size::new(info.width, info.height), data))
+	}
+
+	/// Compares frames channel by channel,
+	/// channels differing by more than `tolerance` are counted as mismatch
+	pub fn compare(&self, expected: &Self, tolerance: u8) -> FrameDiff {
+		if self.size != expected.size {
+			return FrameDiff::SizeMismatch {
+				actual: self.size,
+				expected: expected.size
+			};
+		}
+		let mut mismatched_pixels = 0;
+		let mut max_difference = 0;
+		let mut diff = Vec::with_capacity(self.data.len());
+
+		for (actual, expected) in self.data
+			.chunks_exact(4)
+			.zip(expected.data.chunks_exact(4))
+		{
+			let difference = actual.iter()
+				.zip(expected)
+				.map(|(a, e)| a.abs_diff(*e))
+				.max()
+				.unwrap_or(0);
+			max_difference = max_difference.max(difference);
+
+			if difference > tolerance {
+				mismatched_pixels += 1;
+				diff.extend_from_slice(&[255, 0, 0, 255]);
+			}
+			else {
+				// Dimmed grayscale of the actual pixel for context
+				let luma = (actual[0] as u32 + actual[1] as u32 + actual[2] as u32) / 12;
+				diff.extend_from_slice(&[luma as u8, luma as u8, luma as u8, 255]);
+			}
+		}
+		FrameDiff::Compared {
+			mismatched_pixels,
+			max_difference,
+			image: Self::new(self.size, diff)
+		}
+	}
+}
+
+
+pub enum FrameDiff {
+	SizeMismatch {
+		actual: Size<u32>,
+		expected: Size<u32>
+	},
+	Compared {
+		mismatched_pixels: usize,
+		max_difference: u8,
+		/// Mismatched pixels are red, matching pixels are dimmed grayscale
+		image: CapturedFrame
+	}
+}
+
+impl FrameDiff {
+	pub fn is_match(&self) -> bool {
+		matches!(self, Self::Compared { mismatched_pixels: 0, .. })
+	}
+}
+
+impl fmt::Display for FrameDiff {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::SizeMismatch { actual, expected } => write!(
+				f, "Frame size {}x{} does not match expected {}x{}",
+				actual.width, actual.height, expected.width, expected.height
+			),
+			Self::Compared { mismatched_pixels, max_difference, .. } => write!(
+				f, "{} mismatched pixels, max channel difference {}",
+				mismatched_pixels, max_difference
+			)
+		}
+	}
+}
+
+
+#[derive(Debug)]
+pub enum CaptureError {
+	Surface(SurfaceError),
+	MissingTexture(Box<str>),
+	/// Transient texture shares memory with a resource used later in the frame,
+	/// so its contents are overwritten before they can be read back
+	AliasedTexture(Box<str>),
+	/// Texture was created without [`wgpu::TextureUsages::COPY_SRC`]
+	NotCopyable,
+	UnsupportedFormat(TextureFormat),
+	BufferMap(BufferAsyncError),
+	Poll(PollError),
+	Encoding(png::EncodingError),
+	Decoding(png::DecodingError),
+	UnsupportedImage(png::ColorType, png::BitDepth),
+	Io(io::Error)
+}
+
+impl fmt::Display for CaptureError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Surface(err) => write!(f, "Failed to obtain frame texture: {}", err),
+			Self::MissingTexture(name) => write!(f, "Missing texture {}", name),
+			Self::AliasedTexture(name) => {
+				write!(f, "Texture {} is overwritten by an aliased resource later in the frame", name)
+			}
+			Self::NotCopyable => write!(f, "Texture can not be copied from"),
+			Self::UnsupportedFormat(format) => write!(f, "Unsupported capture format {:?}", format),
+			Self::BufferMap(err) => write!(f, "Failed to map readback buffer: {}", err),
+			Self::Poll(err) => write!(f, "Failed to wait for readback: {}", err),
+			Self::Encoding(err) => write!(f, "Failed to encode png: {}", err),
+			Self::Decoding(err) => write!(f, "Failed to decode png: {}", err),
+			Self::UnsupportedImage(color, depth) => write!(
+				f, "Unsupported image {:?} with bit depth {:?}, expected 8-bit RGBA", color, depth
+			),
+			Self::Io(err) => write!(f, "Failed to write image: {}", err)
+		}
+	}
+}
+
+impl error::Error for CaptureError {}
+
+macro_rules! impl_from_error {
+	($error:ty, $variant:ident) => {
+		impl From<$error> for CaptureError {
+			fn from(value: $error) -> Self {
+				Self::$variant(value)
+			}
+		}
+	};
+}
+
+impl_from_error!(SurfaceError, Surface);
+impl_from_error!(BufferAsyncError, BufferMap);
+impl_from_error!(PollError, Poll);
+impl_from_error!(png::EncodingError, Encoding);
+impl_from_error!(png::DecodingError, Decoding);
+impl_from_error!(io::Error, Io);
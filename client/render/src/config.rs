@@ -1,29 +1,56 @@
+use core::num::NonZero;
+use std::{path::PathBuf, thread};
 use default::default;
 
-pub use wgpu::{Backends, Features, InstanceFlags, Label, Limits, MemoryHints, PowerPreference};
-use wgpu::{DeviceDescriptor, InstanceDescriptor, RequestAdapterOptions, Trace};
+pub use wgpu::{
+	Backend, Backends, DeviceType, Features, InstanceFlags, Label, Limits, MemoryHints,
+	PowerPreference
+};
+use wgpu::{Adapter, DeviceDescriptor, InstanceDescriptor, Trace};
+
+use crate::core::negotiate_limits;
 
 
 pub struct GpuContextConfig<'label> {
 	pub instance_flags: InstanceFlags,
 	pub backends: Backends,
 	pub power_preference: PowerPreference,
+	pub adapter_policy: AdapterPolicy,
 	pub device_label: Label<'label>,
 	pub required_features: Features,
+	/// Enabled only if supported by adapter
+	pub optional_features: Features,
 	pub required_limits: Limits,
-	pub memory_hints: MemoryHints
+	/// Device gets best adapter limits up to the cap,
+	/// all adapter limits are requested if there is no cap
+	pub limits_cap: Option<Limits>,
+	pub memory_hints: MemoryHints,
+	/// Compiled pipelines are cached in a file per adapter in this directory,
+	/// `Features::PIPELINE_CACHE` is requested if supported
+	pub pipeline_cache_dir: Option<PathBuf>,
+	pub pipeline_compilation: PipelineCompilation,
+	/// Render asset manifest file used instead of the embedded one,
+	/// it is reloaded on change with shader hot reload
+	pub asset_manifest: Option<PathBuf>
 }
 
 impl Default for GpuContextConfig<'_> {
 	fn default() -> Self {
 		Self {
 			instance_flags: InstanceFlags::default(),
-			backends: Backends::VULKAN,
+			// GL is a fallback, Vulkan adapters are preferred by adapter policy
+			backends: Backends::VULKAN | Backends::GL,
 			power_preference: PowerPreference::HighPerformance,
+			adapter_policy: AdapterPolicy::default(),
 			device_label: None,
 			required_features: Features::empty(),
+			optional_features: Features::empty(),
 			required_limits: Limits::default(),
-			memory_hints: MemoryHints::Performance
+			limits_cap: None,
+			memory_hints: MemoryHints::Performance,
+			pipeline_cache_dir: None,
+			pipeline_compilation: PipelineCompilation::Blocking,
+			asset_manifest: None
 		}
 	}
 }
@@ -55,6 +82,11 @@ impl<'l> GpuContextConfig<'l> {
 		self
 	}
 
+	pub fn adapter_policy(mut self, adapter_policy: AdapterPolicy) -> Self {
+		self.adapter_policy = adapter_policy;
+		self
+	}
+
 	pub fn device_label(mut self, label: &'l str) -> Self {
 		self.device_label = Some(label);
 		self
@@ -70,10 +102,45 @@ impl<'l> GpuContextConfig<'l> {
 		self
 	}
 
+	pub fn add_optional_features(mut self, features: Features) -> Self {
+		self.optional_features |= features;
+		self
+	}
+
+	pub fn optional_features(mut self, features: Features) -> Self {
+		self.optional_features = features;
+		self
+	}
+
+	pub fn required_limits(mut self, limits: Limits) -> Self {
+		self.required_limits = limits;
+		self
+	}
+
+	pub fn limits_cap(mut self, limits: Limits) -> Self {
+		self.limits_cap = Some(limits);
+		self
+	}
+
 	pub fn memory_hints(mut self, memory_hints: MemoryHints) -> Self {
 		self.memory_hints = memory_hints;
 		self
 	}
+
+	pub fn pipeline_cache_dir(mut self, directory: impl Into<PathBuf>) -> Self {
+		self.pipeline_cache_dir = Some(directory.into());
+		self
+	}
+
+	pub fn pipeline_compilation(mut self, compilation: PipelineCompilation) -> Self {
+		self.pipeline_compilation = compilation;
+		self
+	}
+
+	pub fn asset_manifest(mut self, path: impl Into<PathBuf>) -> Self {
+		self.asset_manifest = Some(path.into());
+		self
+	}
 }
 
 impl GpuContextConfig<'_> {
@@ -85,21 +152,96 @@ impl GpuContextConfig<'_> {
 		}
 	}
 
-	pub(crate) fn request_adapter_options<'s>(&self) -> RequestAdapterOptions<'_, 's> {
-		RequestAdapterOptions {
-			power_preference: self.power_preference,
-			force_fallback_adapter: false,
-			compatible_surface: None
-		}
-	}
-
-	pub(crate) fn device_descriptor(&self) -> DeviceDescriptor<'_> {
+	/// Adapter must support required features and limits
+	pub(crate) fn device_descriptor(&self, adapter: &Adapter) -> DeviceDescriptor<'_> {
 		DeviceDescriptor {
 			label: self.device_label,
-			required_features: self.required_features,
-			required_limits: self.required_limits.clone(),
+			required_features: self.required_features
+				| (self.optional_features | self.pipeline_cache_features()) & adapter.features(),
+			required_limits: negotiate_limits(
+				&self.required_limits,
+				self.limits_cap.as_ref(),
+				&adapter.limits()
+			),
 			memory_hints: self.memory_hints.clone(),
 			trace: Trace::Off,
 		}
 	}
+
+	fn pipeline_cache_features(&self) -> Features {
+		match self.pipeline_cache_dir {
+			Some(_) => Features::PIPELINE_CACHE,
+			None => Features::empty()
+		}
+	}
+}
+
+
+/// How render and compute pipelines of render assets are compiled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelineCompilation {
+	/// Renderer is created once all pipelines are compiled
+	Blocking,
+	/// Pipelines are compiled by worker threads after renderer is created,
+	/// passes are skipped until their pipelines are ready
+	Async {
+		workers: NonZero<usize>
+	}
+}
+
+impl PipelineCompilation {
+	/// Asynchronous compilation with a worker per available core
+	pub fn parallel() -> Self {
+		let workers = thread::available_parallelism().unwrap_or(NonZero::<usize>::MIN);
+		Self::Async { workers }
+	}
+}
+
+
+/// Ranks adapters that support surface, required features and limits.
+/// Adapters are compared by preferred name, then vendor,
+/// then device type according to power preference and then by backend order
+pub struct AdapterPolicy {
+	/// Case insensitive substring of preferred adapter name
+	pub name: Option<String>,
+	/// PCI vendor id of preferred adapter
+	pub vendor: Option<u32>,
+	/// Software (CPU) adapters are used only if there are no other adapters
+	pub allow_software: bool,
+	/// Backends in order of preference, unlisted backends are least preferred
+	pub backend_order: Vec<Backend>
+}
+
+impl Default for AdapterPolicy {
+	fn default() -> Self {
+		Self {
+			name: None,
+			vendor: None,
+			allow_software: true,
+			backend_order: vec![Backend::Vulkan, Backend::Metal, Backend::Dx12, Backend::Gl]
+		}
+	}
+}
+
+// Chaining mutations
+impl AdapterPolicy {
+	pub fn name(mut self, name: impl Into<String>) -> Self {
+		self.name = Some(name.into());
+		self
+	}
+
+	pub fn vendor(mut self, vendor: u32) -> Self {
+		self.vendor = Some(vendor);
+		self
+	}
+
+	pub fn allow_software(mut self, allow_software: bool) -> Self {
+		self.allow_software = allow_software;
+		self
+	}
+
+	pub fn backend_order(mut self, backend_order: Vec<Backend>) -> Self {
+		self.backend_order = backend_order;
+		self
+	}
 }
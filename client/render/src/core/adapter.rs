@@ -0,0 +1,198 @@
+use log::info;
+
+use wgpu::{Adapter, AdapterInfo, Backend, DeviceType, Instance, PowerPreference, Surface};
+
+use crate::{AdapterPolicy, AdapterRejection, GpuContextConfig, InitError, RejectedAdapter};
+
+use super::unsupported_limits;
+
+
+/// Picks the best adapter according to [`AdapterPolicy`]
+/// among adapters compatible with `surface` and supporting required features and limits
+pub(crate) fn select_adapter(
+	instance: &Instance,
+	config: &GpuContextConfig,
+	surface: Option<&Surface>
+) -> Result<Adapter, InitError> {
+	let mut rejected = Vec::new();
+	let mut candidates = Vec::new();
+	for adapter in instance.enumerate_adapters(config.backends) {
+		match check_adapter(&adapter, config, surface) {
+			Ok(()) => candidates.push(adapter),
+			Err(reason) => rejected.push(RejectedAdapter {
+				adapter: adapter.get_info(),
+				reason
+			})
+		}
+	}
+
+	let adapter = candidates.into_iter()
+		.min_by_key(|adapter| rank(
+			&adapter.get_info(),
+			&config.adapter_policy,
+			config.power_preference
+		))
+		.ok_or(InitError::NoAdapter {
+			backends: config.backends,
+			rejected
+		})?;
+
+	let info = adapter.get_info();
+	info!(
+		"Using adapter {} (vendor {:#06x}, device {:#06x}, {:?}, {:?}, driver {} {})",
+		info.name, info.vendor, info.device, info.device_type, info.backend,
+		info.driver, info.driver_info
+	);
+	Ok(adapter)
+}
+
+fn check_adapter(
+	adapter: &Adapter,
+	config: &GpuContextConfig,
+	surface: Option<&Surface>
+) -> Result<(), AdapterRejection> {
+	let policy = &config.adapter_policy;
+	if !policy.allow_software && adapter.get_info().device_type == DeviceType::Cpu {
+		return Err(AdapterRejection::Software);
+	}
+	if surface.is_some_and(|surface| !adapter.is_surface_supported(surface)) {
+		return Err(AdapterRejection::IncompatibleSurface);
+	}
+
+	let available = adapter.features();
+	let missing = config.required_features.difference(available);
+	if !missing.is_empty() {
+		return Err(AdapterRejection::UnsupportedFeatures { missing, available });
+	}
+
+	let available = adapter.limits();
+	let unsupported = unsupported_limits(&config.required_limits, &available);
+	if !unsupported.is_empty() {
+		return Err(AdapterRejection::UnsupportedLimits {
+			unsupported,
+			available: Box::new(available)
+		});
+	}
+	Ok(())
+}
+
+/// Lower is better
+fn rank(
+	info: &AdapterInfo,
+	policy: &AdapterPolicy,
+	power_preference: PowerPreference
+) -> (bool, bool, usize, usize) {
+	let name_mismatch = policy.name.as_ref().is_some_and(|name| !info.name
+		.to_lowercase()
+		.contains(&name.to_lowercase())
+	);
+	let vendor_mismatch = policy.vendor.is_some_and(|vendor| info.vendor != vendor);
+	(
+		name_mismatch,
+		vendor_mismatch,
+		device_type_rank(info.device_type, power_preference),
+		backend_rank(info.backend, &policy.backend_order)
+	)
+}
+
+fn device_type_rank(device_type: DeviceType, power_preference: PowerPreference) -> usize {
+	let (first, second) = match power_preference {
+		PowerPreference::LowPower => (DeviceType::IntegratedGpu, DeviceType::DiscreteGpu),
+		_ => (DeviceType::DiscreteGpu, DeviceType::IntegratedGpu)
+	};
+	[first, second, DeviceType::VirtualGpu, DeviceType::Other, DeviceType::Cpu]
+		.iter()
+		.position(|&ty| ty == device_type)
+		.unwrap_or(usize::MAX)
+}
+
+fn backend_rank(backend: Backend, order: &[Backend]) -> usize {
+	order.iter()
+		.position(|&preferred| preferred == backend)
+		.unwrap_or(order.len())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use default::default;
+
+	use super::*;
+
+
+	fn info(name: &str, vendor: u32, device_type: DeviceType, backend: Backend) -> AdapterInfo {
+		AdapterInfo {
+			name: name.to_owned(),
+			vendor,
+			device: 0,
+			device_type,
+			driver: String::new(),
+			driver_info: String::new(),
+			backend
+		}
+	}
+
+	/// Names of adapters from best to worst
+	fn ranked<'a>(
+		adapters: &'a [AdapterInfo],
+		policy: &AdapterPolicy,
+		power_preference: PowerPreference
+	) -> Vec<&'a str> {
+		let mut adapters = adapters.iter().collect::<Vec<_>>();
+		adapters.sort_by_key(|info| rank(info, policy, power_preference));
+		adapters.into_iter().map(|info| info.name.as_str()).collect()
+	}
+
+	#[test]
+	fn device_types_follow_power_preference() {
+		let adapters = [
+			info("cpu", 0, DeviceType::Cpu, Backend::Vulkan),
+			info("integrated", 0, DeviceType::IntegratedGpu, Backend::Vulkan),
+			info("discrete", 0, DeviceType::DiscreteGpu, Backend::Vulkan),
+			info("virtual", 0, DeviceType::VirtualGpu, Backend::Vulkan)
+		];
+		let policy = AdapterPolicy::default();
+		assert_eq!(
+			ranked(&adapters, &policy, PowerPreference::HighPerformance),
+			["discrete", "integrated", "virtual", "cpu"]
+		);
+		assert_eq!(
+			ranked(&adapters, &policy, PowerPreference::LowPower),
+			["integrated", "discrete", "virtual", "cpu"]
+		);
+	}
+
+	#[test]
+	fn name_and_vendor_outrank_device_type() {
+		let adapters = [
+			info("Fast GPU", 1, DeviceType::DiscreteGpu, Backend::Vulkan),
+			info("Slow GPU", 2, DeviceType::IntegratedGpu, Backend::Vulkan),
+			info("Other slow GPU", 1, DeviceType::IntegratedGpu, Backend::Vulkan)
+		];
+		let by_name = AdapterPolicy { name: Some("slow gpu".into()), ..default() };
+		assert_eq!(
+			ranked(&adapters, &by_name, PowerPreference::HighPerformance),
+			["Slow GPU", "Other slow GPU", "Fast GPU"]
+		);
+		let by_name_and_vendor = AdapterPolicy { vendor: Some(1), ..by_name };
+		assert_eq!(
+			ranked(&adapters, &by_name_and_vendor, PowerPreference::HighPerformance),
+			["Other slow GPU", "Slow GPU", "Fast GPU"]
+		);
+	}
+
+	#[test]
+	fn backends_follow_policy_order() {
+		let adapters = [
+			info("gl", 0, DeviceType::DiscreteGpu, Backend::Gl),
+			info("unlisted", 0, DeviceType::DiscreteGpu, Backend::Dx12),
+			info("vulkan", 0, DeviceType::DiscreteGpu, Backend::Vulkan),
+			info("integrated", 0, DeviceType::IntegratedGpu, Backend::Vulkan)
+		];
+		let policy = AdapterPolicy { backend_order: vec![Backend::Vulkan, Backend::Gl], ..default() };
+		assert_eq!(
+			ranked(&adapters, &policy, PowerPreference::HighPerformance),
+			["vulkan", "gl", "unlisted", "integrated"]
+		);
+	}
+}
@@ -1,17 +1,17 @@
 use wgpu::{CommandEncoder, Queue};
 
-use super::SwapchainTexture;
+use super::TargetTexture;
 
 
 pub(crate) struct FrameContext {
 	pub encoder: CommandEncoder,
-	pub texture: SwapchainTexture
+	pub texture: TargetTexture
 }
 
 impl FrameContext {
 	pub fn new(
 		encoder: CommandEncoder,
-		texture: SwapchainTexture
+		texture: TargetTexture
 	) -> Self {
 		Self { encoder, texture }
 	}
@@ -0,0 +1,145 @@
+use wgpu::Limits;
+
+use crate::UnsupportedLimit;
+
+
+/// Best limits of `available` not exceeding `cap`, but not worse than `required`.
+/// Subgroup sizes are taken from `available` as they describe the adapter
+pub(crate) fn negotiate_limits(required: &Limits, cap: Option<&Limits>, available: &Limits) -> Limits {
+	let mut limits = available.clone();
+	if let Some(cap) = cap {
+		combine_limits(&mut limits, cap, Preference::Worse);
+	}
+	combine_limits(&mut limits, required, Preference::Better);
+	limits
+}
+
+/// Limits of `required` exceeding `available`
+pub(crate) fn unsupported_limits(required: &Limits, available: &Limits) -> Vec<UnsupportedLimit> {
+	let mut unsupported = Vec::new();
+	required.check_limits_with_fail_fn(available, false, |name, requested, allowed| {
+		unsupported.push(UnsupportedLimit { name, requested, allowed });
+	});
+	unsupported
+}
+
+#[derive(Clone, Copy)]
+enum Preference {
+	Better,
+	Worse
+}
+
+/// Replaces each limit with the better or worse of two values,
+/// higher is better for maximums and lower is better for alignments
+fn combine_limits(limits: &mut Limits, other: &Limits, preference: Preference) {
+	macro_rules! combine {
+		(max: $($name:ident),*; min: $($alignment:ident),*) => {
+			$(limits.$name = match preference {
+				Preference::Better => limits.$name.max(other.$name),
+				Preference::Worse => limits.$name.min(other.$name)
+			};)*
+			$(limits.$alignment = match preference {
+				Preference::Better => limits.$alignment.min(other.$alignment),
+				Preference::Worse => limits.$alignment.max(other.$alignment)
+			};)*
+		};
+	}
+
+	combine!(
+		max:
+			max_texture_dimension_1d,
+			max_texture_dimension_2d,
+			max_texture_dimension_3d,
+			max_texture_array_layers,
+			max_bind_groups,
+			max_bindings_per_bind_group,
+			max_dynamic_uniform_buffers_per_pipeline_layout,
+			max_dynamic_storage_buffers_per_pipeline_layout,
+			max_sampled_textures_per_shader_stage,
+			max_samplers_per_shader_stage,
+			max_storage_buffers_per_shader_stage,
+			max_storage_textures_per_shader_stage,
+			max_uniform_buffers_per_shader_stage,
+			max_binding_array_elements_per_shader_stage,
+			max_binding_array_sampler_elements_per_shader_stage,
+			max_uniform_buffer_binding_size,
+			max_storage_buffer_binding_size,
+			max_vertex_buffers,
+			max_buffer_size,
+			max_vertex_attributes,
+			max_vertex_buffer_array_stride,
+			max_inter_stage_shader_components,
+			max_color_attachments,
+			max_color_attachment_bytes_per_sample,
+			max_compute_workgroup_storage_size,
+			max_compute_invocations_per_workgroup,
+			max_compute_workgroup_size_x,
+			max_compute_workgroup_size_y,
+			max_compute_workgroup_size_z,
+			max_compute_workgroups_per_dimension,
+			max_push_constant_size,
+			max_non_sampler_bindings;
+		min:
+			min_uniform_buffer_offset_alignment,
+			min_storage_buffer_offset_alignment
+	);
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+
+	#[test]
+	fn negotiated_limits_are_capped() {
+		let available = Limits { max_texture_dimension_2d: 16384, max_bind_groups: 8, ..Limits::default() };
+		let cap = Limits { max_texture_dimension_2d: 4096, ..Limits::default() };
+		let limits = negotiate_limits(&Limits::downlevel_defaults(), Some(&cap), &available);
+		assert_eq!(limits.max_texture_dimension_2d, 4096);
+		// Default cap is below available
+		assert_eq!(limits.max_bind_groups, 4);
+	}
+
+	#[test]
+	fn negotiated_limits_are_not_worse_than_required() {
+		let available = Limits::default();
+		let required = Limits { max_texture_dimension_2d: 4096, ..Limits::downlevel_defaults() };
+		let cap = Limits { max_texture_dimension_2d: 2048, ..Limits::default() };
+		let limits = negotiate_limits(&required, Some(&cap), &available);
+		assert_eq!(limits.max_texture_dimension_2d, 4096);
+	}
+
+	#[test]
+	fn negotiated_limits_default_to_available() {
+		let available = Limits { max_texture_dimension_2d: 16384, ..Limits::default() };
+		let limits = negotiate_limits(&Limits::downlevel_defaults(), None, &available);
+		assert_eq!(limits, available);
+	}
+
+	#[test]
+	fn lower_alignments_are_better() {
+		let available = Limits { min_uniform_buffer_offset_alignment: 64, ..Limits::default() };
+		let cap = Limits { min_uniform_buffer_offset_alignment: 256, ..Limits::default() };
+		let limits = negotiate_limits(&Limits::downlevel_defaults(), Some(&cap), &available);
+		assert_eq!(limits.min_uniform_buffer_offset_alignment, 256);
+		let limits = negotiate_limits(&Limits::downlevel_defaults(), None, &available);
+		assert_eq!(limits.min_uniform_buffer_offset_alignment, 64);
+	}
+
+	#[test]
+	fn limits_above_available_are_unsupported() {
+		let available = Limits::downlevel_defaults();
+		let required = Limits {
+			max_texture_dimension_2d: available.max_texture_dimension_2d * 2,
+			min_storage_buffer_offset_alignment: available.min_storage_buffer_offset_alignment / 2,
+			..available.clone()
+		};
+		let unsupported = unsupported_limits(&required, &available);
+		let names = unsupported.iter().map(|limit| limit.name).collect::<Vec<_>>();
+		assert_eq!(names, ["max_texture_dimension_2d", "min_storage_buffer_offset_alignment"]);
+		assert_eq!(unsupported[0].requested, u64::from(required.max_texture_dimension_2d));
+		assert_eq!(unsupported[0].allowed, u64::from(available.max_texture_dimension_2d));
+		assert!(unsupported_limits(&available, &available).is_empty());
+	}
+}
@@ -1,39 +1,71 @@
 pub(crate) use surface::*;
 pub(crate) use frame::*;
+pub(crate) use target::*;
+pub(crate) use readback::*;
+pub(crate) use limits::*;
 
 pub mod util;
+mod adapter;
 mod surface;
 mod frame;
+mod target;
+mod readback;
+mod limits;
 
 
-use wgpu::{Adapter, CommandEncoder, CommandEncoderDescriptor, Device, Instance, Queue};
+use wgpu::{
+	Adapter, CommandEncoder, CommandEncoderDescriptor, Device, Features, Instance, Limits, Queue,
+	Surface
+};
 
-use crate::GpuContextConfig;
+use log::info;
+
+use crate::{GpuContextConfig, InitError};
+
+use adapter::select_adapter;
 
 
 pub(crate) struct GpuContext {
 	pub instance: Instance,
 	pub adapter: Adapter,
 	pub device: Device,
-	pub queue: Queue
+	pub queue: Queue,
+	pub capabilities: GpuCapabilities
 }
 
-impl GpuContext {
-	pub async fn new(config: GpuContextConfig<'_>) -> Self {
-		let instance = Instance::new(&config.instance_descriptor());
+/// Features and limits negotiated with the adapter
+pub(crate) struct GpuCapabilities {
+	pub features: Features,
+	pub limits: Limits
+}
 
-		let adapter = instance.request_adapter(&config.request_adapter_options())
-			.await
-			.expect("Failed to find an appropriate adapter");
+impl GpuContext {
+	/// Instance should be created from [`GpuContextConfig::instance_descriptor`].
+	/// Selected adapter is compatible with `surface` if given
+	pub async fn new(
+		instance: Instance,
+		config: &GpuContextConfig<'_>,
+		surface: Option<&Surface<'_>>
+	) -> Result<Self, InitError> {
+		let adapter = select_adapter(&instance, config, surface)?;
 
 		let (device, queue) = adapter
-			.request_device(&config.device_descriptor())
+			.request_device(&config.device_descriptor(&adapter))
 			.await
-			.expect("Failed to request device");
+			.map_err(|error| InitError::RequestDevice {
+				adapter: Box::new(adapter.get_info()),
+				error
+			})?;
 
-		Self {
-			instance, adapter, device, queue
-		}
+		info!("Device features: {:?}", device.features());
+		let capabilities = GpuCapabilities {
+			features: device.features(),
+			limits: device.limits()
+		};
+
+		Ok(Self {
+			instance, adapter, device, queue, capabilities
+		})
 	}
 
 	pub fn create_encoder(&self, label: &str) -> CommandEncoder {
@@ -41,4 +73,23 @@ impl GpuContext {
 			label: Some(label)
 		})
 	}
+
+	/// Headless context for unit tests, `None` with a note on stderr if there is no adapter.
+	/// Panics instead if `STARFLOW_REQUIRE_GPU` is set
+	#[cfg(test)]
+	pub fn for_tests() -> Option<Self> {
+		let config = GpuContextConfig::default();
+		let instance = Instance::new(&config.instance_descriptor());
+		match futures_lite::future::block_on(Self::new(instance, &config, None)) {
+			Ok(context) => Some(context),
+			Err(err @ InitError::NoAdapter { .. }) => {
+				if std::env::var_os("STARFLOW_REQUIRE_GPU").is_some() {
+					panic!("No adapter while STARFLOW_REQUIRE_GPU is set: {}", err);
+				}
+				eprintln!("SKIPPED: {}", err);
+				None
+			}
+			Err(err) => panic!("Failed to create GPU context: {}", err)
+		}
+	}
 }
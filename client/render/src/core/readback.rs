@@ -0,0 +1,98 @@
+use std::sync::mpsc;
+
+use wgpu::{
+	Buffer, BufferDescriptor, BufferUsages, CommandEncoder, Device, MapMode, Origin3d, PollType,
+	TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect,
+	TextureFormat, TextureUsages, COPY_BYTES_PER_ROW_ALIGNMENT
+};
+
+use starflow_util::Size;
+
+use crate::capture::{CaptureError, CapturedFrame};
+
+
+const BYTES_PER_PIXEL: u32 = 4;
+
+/// Buffer a texture is copied into to be read on the CPU
+pub(crate) struct TextureReadback {
+	buffer: Buffer,
+	size: Size<u32>,
+	padded_bytes_per_row: u32,
+	bgra: bool
+}
+
+impl TextureReadback {
+	/// Records copy of the whole texture into a new readback buffer.
+	/// Texture must be created with [`wgpu::TextureUsages::COPY_SRC`]
+	pub fn encode(
+		device: &Device,
+		encoder: &mut CommandEncoder,
+		texture: &Texture
+	) -> Result<Self, CaptureError> {
+		if !texture.usage().contains(TextureUsages::COPY_SRC) {
+			return Err(CaptureError::NotCopyable);
+		}
+		let bgra = match texture.format() {
+			TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+			TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+			format => return Err(CaptureError::UnsupportedFormat(format))
+		};
+		let size = Size::new(texture.width(), texture.height());
+		let padded_bytes_per_row = (size.width * BYTES_PER_PIXEL)
+			.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+
+		let buffer = device.create_buffer(&BufferDescriptor {
+			label: Some("readback_buffer"),
+			size: (padded_bytes_per_row * size.height) as u64,
+			usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+			mapped_at_creation: false
+		});
+		encoder.copy_texture_to_buffer(
+			TexelCopyTextureInfo {
+				texture,
+				mip_level: 0,
+				origin: Origin3d::ZERO,
+				aspect: TextureAspect::All
+			},
+			TexelCopyBufferInfo {
+				buffer: &buffer,
+				layout: TexelCopyBufferLayout {
+					offset: 0,
+					bytes_per_row: Some(padded_bytes_per_row),
+					rows_per_image: Some(size.height)
+				}
+			},
+			size.into()
+		);
+		Ok(Self { buffer, size, padded_bytes_per_row, bgra })
+	}
+
+	/// Blocks until copy is finished. Encoder with the copy must be already submitted
+	pub fn read(self, device: &Device) -> Result<CapturedFrame, CaptureError> {
+		let (sender, receiver) = mpsc::channel();
+		self.buffer.map_async(MapMode::Read, .., move |result| {
+			let _ = sender.send(result);
+		});
+		device.poll(PollType::Wait)?;
+		receiver
+			.recv()
+			.expect("Map callback is called during poll")?;
+
+		let row_bytes = (self.size.width * BYTES_PER_PIXEL) as usize;
+		let mut data = Vec::with_capacity(row_bytes * self.size.height as usize);
+		{
+			let mapped = self.buffer.get_mapped_range(..);
+			for row in mapped.chunks_exact(self.padded_bytes_per_row as usize) {
+				data.extend_from_slice(&row[..row_bytes]);
+			}
+		}
+		self.buffer.unmap();
+
+		if self.bgra {
+			for pixel in data.chunks_exact_mut(BYTES_PER_PIXEL as usize) {
+				pixel.swap(0, 2);
+			}
+		}
+		Ok(CapturedFrame::new(self.size, data))
+	}
+}
@@ -1,39 +1,41 @@
-use default::default;
-
 use wgpu::{
-	Color, Device, LoadOp, RenderPassColorAttachment, StoreOp, Surface,
-	SurfaceConfiguration, SurfaceError, SurfaceTarget, SurfaceTexture,
-	TextureFormat, TextureView
+	Device, Surface, SurfaceConfiguration, SurfaceError, TextureFormat, TextureUsages
 };
 
 use starflow_util::Size;
-use crate::core::GpuContext;
+use crate::{
+	core::{util::SurfaceSource, GpuContext, TargetTexture},
+	InitError
+};
 
 
 pub(crate) struct RenderSurface<'window> {
 	surface: Surface<'window>,
-	config: SurfaceConfiguration
+	config: SurfaceConfiguration,
+	source: Option<SurfaceSource<'window>>
 }
 
 impl<'w> RenderSurface<'w> {
-	/// Returns none if surface is not supported by adapter
 	pub fn configured(
-		target: impl Into<SurfaceTarget<'w>>,
+		surface: Surface<'w>,
 		size: Size<u32>, 
+		source: Option<SurfaceSource<'w>>,
 		context: &GpuContext
-	) -> Option<Self> {
-		let surface = context.instance
-			.create_surface(target).ok()?;
-
-		let config = surface.get_default_config(
+	) -> Result<Self, InitError> {
+		let mut config = surface.get_default_config(
 			&context.adapter,
 			// wgpu will panic if one of dimensions is zero
 			size.width.max(1), 
 			size.height.max(1)
-		)?;
+		).ok_or_else(|| InitError::UnsupportedSurface {
+			adapter: Box::new(context.adapter.get_info())
+		})?;
+		// Allows frame capture when supported
+		let capabilities = surface.get_capabilities(&context.adapter);
+		config.usage |= capabilities.usages & TextureUsages::COPY_SRC;
 
 		surface.configure(&context.device, &config);
-		Some(Self { surface, config })
+		Ok(Self { surface, config, source })
 	}
 
 	pub fn texture_format(&self) -> TextureFormat {
@@ -46,7 +48,7 @@ impl<'w> RenderSurface<'w> {
 
 	pub fn get_swapchain_texture(
 		&self, device: &Device
-	) -> Result<SwapchainTexture, SurfaceError> {
+	) -> Result<TargetTexture, SurfaceError> {
 		let texture = match self.surface.get_current_texture() {
 			Ok(texture) => texture,
 			Err(SurfaceError::Outdated) => {
@@ -55,53 +57,32 @@ impl<'w> RenderSurface<'w> {
 			}
 			Err(e) => return Err(e)
 		};
-		let view = texture.texture
-			.create_view(&default());
-		Ok(SwapchainTexture { texture, view })
+		Ok(TargetTexture::swapchain(texture))
 	}
 
-	#[allow(dead_code)]
 	pub fn resize(&mut self, size: Size<u32>, device: &Device) {
 		self.config.width = size.width.max(1);
 		self.config.height = size.height.max(1);
 		self.reconfigure(device);
 	}
 
+	/// Creates new surface from the source if there is one,
+	/// otherwise reconfigures existing surface
+	pub fn recreate(&mut self, context: &GpuContext) {
+		let surface = self.source.as_ref()
+			.and_then(|source| context.instance
+				.create_surface(source())
+				.ok()
+			);
+		if let Some(surface) = surface {
+			self.surface = surface;
+		}
+		self.reconfigure(&context.device);
+	}
+
 	#[inline(always)]
 	fn reconfigure(&self, device: &Device) {
 		self.surface.configure(device, &self.config);
 	}
 }
 
-
-pub(crate) struct SwapchainTexture {
-	texture: SurfaceTexture,
-	view: TextureView
-}
-
-impl SwapchainTexture {
-	#[inline]
-	pub fn width(&self) -> u32 {
-		self.texture.texture.width()
-	}
-
-	#[inline]
-	pub fn height(&self) -> u32 {
-		self.texture.texture.height()
-	}
-
-	pub fn clear_attachment(&'_ self, color: Color) -> RenderPassColorAttachment<'_> {
-		RenderPassColorAttachment {
-			view: &self.view, 
-			resolve_target: None, 
-			ops: wgpu::Operations { 
-				load: LoadOp::Clear(color), 
-				store: StoreOp::Store
-			}
-		}
-	}
-
-	pub fn present(self) {
-		self.texture.present();
-	}
-}
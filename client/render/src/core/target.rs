@@ -0,0 +1,170 @@
+use default::default;
+
+use wgpu::{
+	Color, Device, LoadOp, RenderPassColorAttachment, StoreOp, SurfaceError, SurfaceTexture,
+	Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView
+};
+
+use starflow_util::Size;
+
+use super::{GpuContext, RenderSurface};
+
+
+/// Final color target of the renderer, either a window surface or an offscreen texture
+pub(crate) enum RenderTarget<'window> {
+	Surface(RenderSurface<'window>),
+	Offscreen(OffscreenTarget)
+}
+
+impl RenderTarget<'_> {
+	pub fn texture_format(&self) -> TextureFormat {
+		match self {
+			Self::Surface(surface) => surface.texture_format(),
+			Self::Offscreen(target) => target.texture_format()
+		}
+	}
+
+	pub fn size(&self) -> Size<u32> {
+		match self {
+			Self::Surface(surface) => surface.size(),
+			Self::Offscreen(target) => target.size()
+		}
+	}
+
+	pub fn get_target_texture(
+		&self, device: &Device
+	) -> Result<TargetTexture, SurfaceError> {
+		match self {
+			Self::Surface(surface) => surface.get_swapchain_texture(device),
+			Self::Offscreen(target) => Ok(target.get_target_texture())
+		}
+	}
+
+	pub fn resize(&mut self, size: Size<u32>, device: &Device) {
+		match self {
+			Self::Surface(surface) => surface.resize(size, device),
+			Self::Offscreen(target) => target.resize(size, device)
+		}
+	}
+
+	/// Recreates lost surface, offscreen target can't be lost
+	pub fn recreate(&mut self, context: &GpuContext) {
+		if let Self::Surface(surface) = self {
+			surface.recreate(context);
+		}
+	}
+}
+
+
+pub(crate) struct OffscreenTarget {
+	texture: Texture
+}
+
+impl OffscreenTarget {
+	pub fn new(
+		size: Size<u32>,
+		format: TextureFormat,
+		device: &Device
+	) -> Self {
+		let texture = device.create_texture(&TextureDescriptor {
+			label: Some("offscreen_target"),
+			// wgpu will panic if one of dimensions is zero
+			size: Size::new(size.width.max(1), size.height.max(1)).into(),
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format,
+			usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+			view_formats: &[]
+		});
+		Self { texture }
+	}
+
+	pub fn texture_format(&self) -> TextureFormat {
+		self.texture.format()
+	}
+
+	pub fn size(&self) -> Size<u32> {
+		Size::new(self.texture.width(), self.texture.height())
+	}
+
+	pub fn get_target_texture(&self) -> TargetTexture {
+		TargetTexture::offscreen(self.texture.clone())
+	}
+
+	pub fn resize(&mut self, size: Size<u32>, device: &Device) {
+		*self = Self::new(size, self.texture_format(), device);
+	}
+}
+
+
+enum FrameTexture {
+	Surface(SurfaceTexture),
+	Offscreen(Texture)
+}
+
+impl FrameTexture {
+	#[inline]
+	fn texture(&self) -> &Texture {
+		match self {
+			Self::Surface(texture) => &texture.texture,
+			Self::Offscreen(texture) => texture
+		}
+	}
+}
+
+
+/// Texture the current frame is rendered into
+pub(crate) struct TargetTexture {
+	texture: FrameTexture,
+	view: TextureView
+}
+
+impl TargetTexture {
+	pub fn swapchain(texture: SurfaceTexture) -> Self {
+		Self::from_frame_texture(FrameTexture::Surface(texture))
+	}
+
+	pub fn offscreen(texture: Texture) -> Self {
+		Self::from_frame_texture(FrameTexture::Offscreen(texture))
+	}
+
+	fn from_frame_texture(texture: FrameTexture) -> Self {
+		let view = texture.texture()
+			.create_view(&default());
+		Self { texture, view }
+	}
+
+	#[inline]
+	pub fn texture(&self) -> &Texture {
+		self.texture.texture()
+	}
+
+	#[inline]
+	pub fn width(&self) -> u32 {
+		self.texture.texture().width()
+	}
+
+	#[inline]
+	pub fn height(&self) -> u32 {
+		self.texture.texture().height()
+	}
+
+	pub fn clear_attachment(&'_ self, color: Color) -> RenderPassColorAttachment<'_> {
+		RenderPassColorAttachment {
+			view: &self.view, 
+			resolve_target: None, 
+			ops: wgpu::Operations { 
+				load: LoadOp::Clear(color), 
+				store: StoreOp::Store
+			}
+		}
+	}
+
+	/// Presents swapchain texture, does nothing for offscreen target
+	pub fn present(self) {
+		if let FrameTexture::Surface(texture) = self.texture {
+			texture.present();
+		}
+	}
+}
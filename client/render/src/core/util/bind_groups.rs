@@ -1,4 +1,6 @@
-use wgpu::{BindGroupEntry, BindingResource, Buffer, BufferBinding, Sampler, TextureView};
+use wgpu::{
+	BindGroupEntry, BindingResource, Buffer, BufferBinding, BufferSlice, Sampler, TextureView
+};
 
 
 pub(crate) trait AsBindGroupEntry {
@@ -15,14 +17,38 @@ impl AsBindGroupEntry for Buffer {
 	}
 }
 
+impl AsBindGroupEntry for BufferSlice<'_> {
+	fn as_bind_group_entry<'a>(&'a self, binding: u32) -> BindGroupEntry<'a> {
+		BindGroupEntry { binding, resource: BindingResource::Buffer((*self).into())}
+	}
+}
+
+impl AsBindGroupEntry for [BufferBinding<'_>] {
+	fn as_bind_group_entry<'a>(&'a self, binding: u32) -> BindGroupEntry<'a> {
+		BindGroupEntry { binding, resource: BindingResource::BufferArray(self)}
+	}
+}
+
 impl AsBindGroupEntry for TextureView {
 	fn as_bind_group_entry<'a>(&'a self, binding: u32) -> BindGroupEntry<'a> {
 		BindGroupEntry { binding, resource: BindingResource::TextureView(self)}
 	}
 }
 
+impl AsBindGroupEntry for [&TextureView] {
+	fn as_bind_group_entry<'a>(&'a self, binding: u32) -> BindGroupEntry<'a> {
+		BindGroupEntry { binding, resource: BindingResource::TextureViewArray(self)}
+	}
+}
+
 impl AsBindGroupEntry for Sampler {
 	fn as_bind_group_entry<'a>(&'a self, binding: u32) -> BindGroupEntry<'a> {
 		BindGroupEntry { binding, resource: BindingResource::Sampler(self)}
 	}
 }
+
+impl AsBindGroupEntry for [&Sampler] {
+	fn as_bind_group_entry<'a>(&'a self, binding: u32) -> BindGroupEntry<'a> {
+		BindGroupEntry { binding, resource: BindingResource::SamplerArray(self)}
+	}
+}
@@ -3,9 +3,13 @@ use wgpu::SurfaceTarget;
 use starflow_util::Size;
 
 
+/// Creates new target for the same window, used to recreate lost surface
+pub type SurfaceSource<'window> = Box<dyn Fn() -> SurfaceTarget<'window> + 'window>;
+
 pub struct SizedSurfaceTarget<'window> {
 	pub target: SurfaceTarget<'window>,
-	pub size: Size<u32>
+	pub size: Size<u32>,
+	pub source: Option<SurfaceSource<'window>>
 }
 
 #[cfg(feature = "winit")]
@@ -22,8 +26,10 @@ mod winit_features {
 	impl<'w> From<Arc<Window>> for SizedSurfaceTarget<'w> {
 		fn from(value: Arc<Window>) -> Self {
 			let size = value.inner_size().into();
+			let window = value.clone();
+			let source = Box::new(move || window.clone().into());
 			let target = value.into();
-			Self { target, size }
+			Self { target, size, source: Some(source) }
 		}
 	}
 }
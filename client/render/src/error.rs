@@ -0,0 +1,141 @@
+use core::{error, fmt};
+
+use wgpu::{
+	AdapterInfo, Backends, CreateSurfaceError, Features, Limits, RequestDeviceError
+};
+
+use crate::{
+	assets::{AssetErrors, ManifestError},
+	BindGroupError, GraphError
+};
+
+
+/// Renderer initialization error, each variant corresponds to a failed step
+#[derive(Debug)]
+pub enum InitError {
+	/// None of adapters for the backends is suitable
+	NoAdapter {
+		backends: Backends,
+		rejected: Vec<RejectedAdapter>
+	},
+	RequestDevice {
+		adapter: Box<AdapterInfo>,
+		error: RequestDeviceError
+	},
+	CreateSurface(CreateSurfaceError),
+	/// Surface can't be presented by the adapter
+	UnsupportedSurface {
+		adapter: Box<AdapterInfo>
+	},
+	Manifest(ManifestError),
+	/// All render assets that failed to be created
+	Assets(AssetErrors),
+	/// Passes or their resources are declared inconsistently
+	Graph(GraphError),
+	/// Bind group doesn't match its layout
+	BindGroup(BindGroupError)
+}
+
+#[derive(Debug)]
+pub struct RejectedAdapter {
+	pub adapter: AdapterInfo,
+	pub reason: AdapterRejection
+}
+
+#[derive(Debug)]
+pub enum AdapterRejection {
+	/// Software adapters are not allowed by adapter policy
+	Software,
+	IncompatibleSurface,
+	UnsupportedFeatures {
+		missing: Features,
+		available: Features
+	},
+	UnsupportedLimits {
+		unsupported: Vec<UnsupportedLimit>,
+		available: Box<Limits>
+	}
+}
+
+#[derive(Debug)]
+pub struct UnsupportedLimit {
+	pub name: &'static str,
+	pub requested: u64,
+	pub allowed: u64
+}
+
+impl fmt::Display for InitError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::NoAdapter { backends, rejected } => {
+				write!(f, "No suitable adapter found for backends {:?}", backends)?;
+				for rejected in rejected {
+					write!(f, "\n  {}: {}", AdapterName(&rejected.adapter), rejected.reason)?;
+				}
+				Ok(())
+			}
+			Self::RequestDevice { adapter, error } => {
+				write!(f, "Failed to request device from adapter {}: {}", AdapterName(adapter), error)
+			}
+			Self::CreateSurface(error) => write!(f, "Failed to create surface: {}", error),
+			Self::UnsupportedSurface { adapter } => {
+				write!(f, "Surface is not supported by adapter {}", AdapterName(adapter))
+			}
+			Self::Manifest(error) => write!(f, "{}", error),
+			Self::Assets(errors) => write!(f, "{}", errors),
+			Self::Graph(error) => write!(f, "Invalid render graph: {}", error),
+			Self::BindGroup(error) => write!(f, "{}", error)
+		}
+	}
+}
+
+impl error::Error for InitError {}
+
+impl fmt::Display for AdapterRejection {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Software => write!(f, "software adapters are not allowed"),
+			Self::IncompatibleSurface => write!(f, "surface is not supported"),
+			Self::UnsupportedFeatures { missing, available } => write!(
+				f, "features {:?} are not supported, available features: {:?}",
+				missing, available
+			),
+			Self::UnsupportedLimits { unsupported, .. } => {
+				write!(f, "limits are not supported:")?;
+				for limit in unsupported {
+					write!(f, " {} (requested {}, allowed {})", limit.name, limit.requested, limit.allowed)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+
+struct AdapterName<'a>(&'a AdapterInfo);
+
+impl fmt::Display for AdapterName<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} ({:?}, {:?})", self.0.name, self.0.backend, self.0.device_type)
+	}
+}
+
+
+/// Fatal renderer error, rendering can't continue
+#[derive(Debug)]
+pub enum RenderError {
+	OutOfMemory,
+	/// Surface is still lost after recreation
+	SurfaceLost
+}
+
+impl fmt::Display for RenderError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::OutOfMemory => write!(f, "Out of memory while acquiring frame texture"),
+			Self::SurfaceLost => write!(f, "Surface is lost and could not be recreated")
+		}
+	}
+}
+
+impl error::Error for RenderError {}
@@ -0,0 +1,563 @@
+pub(crate) use pass::*;
+
+mod pass;
+
+
+use core::{error, fmt};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use default::default;
+
+use wgpu::{Color, RenderPassDescriptor};
+
+use crate::{
+	assets::RenderAssets,
+	core::{FrameContext, GpuCapabilities},
+	resources::{assign_slots, RenderResources, ResourcePlan, TransientResource}
+};
+
+
+pub(crate) struct RenderGraph {
+	/// Passes in execution order
+	passes: Vec<GraphPass>,
+	resources: ResourcePlan
+}
+
+struct GraphPass {
+	pass: Box<dyn RenderPass>,
+	reads: Vec<&'static str>,
+	writes: Vec<&'static str>
+}
+
+/// Work recorded for a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FrameStep {
+	/// Index of pass in execution order
+	Run(usize),
+	/// Final target is cleared in place of skipped writers
+	ClearFinalTarget
+}
+
+impl RenderGraph {
+	pub fn builder() -> RenderGraphBuilder {
+		RenderGraphBuilder { passes: Vec::new() }
+	}
+
+	/// Transient resources to be allocated for the graph
+	pub fn resources(&self) -> &ResourcePlan {
+		&self.resources
+	}
+
+	pub fn run(
+		&self,
+		frame: &mut FrameContext,
+		capabilities: &GpuCapabilities,
+		assets: &RenderAssets,
+		resources: &RenderResources
+	) {
+		for step in self.schedule(|pass| pass.is_ready(assets)) {
+			match step {
+				FrameStep::Run(index) => {
+					self.passes[index].pass.run(frame, capabilities, assets, resources);
+				}
+				FrameStep::ClearFinalTarget => {
+					let attachment = frame.texture.clear_attachment(Color::BLACK);
+					frame.encoder.begin_render_pass(&RenderPassDescriptor {
+						label: Some("clear_final_target"),
+						color_attachments: &[Some(attachment)],
+						..default()
+					});
+				}
+			}
+		}
+	}
+
+	/// Passes that are ready and read no resources of skipped passes.
+	/// If a final target writer is skipped, the target is cleared before the next writer
+	/// that runs, or at the end if none does, so that it never shows stale contents
+	fn schedule(&self, is_ready: impl Fn(&dyn RenderPass) -> bool) -> Vec<FrameStep> {
+		let mut steps = Vec::with_capacity(self.passes.len() + 1);
+		// Resources whose writers were skipped this frame
+		let mut incomplete = HashSet::<&str>::new();
+		let mut final_target_written = false;
+		for (index, GraphPass { pass, reads, writes }) in self.passes.iter().enumerate() {
+			let ready = is_ready(pass.as_ref())
+				&& !reads.iter().any(|resource| incomplete.contains(resource));
+			if !ready {
+				incomplete.extend(writes);
+				continue;
+			}
+			if writes.contains(&FINAL_TARGET) {
+				if !final_target_written && incomplete.contains(&FINAL_TARGET) {
+					steps.push(FrameStep::ClearFinalTarget);
+				}
+				final_target_written = true;
+			}
+			steps.push(FrameStep::Run(index));
+		}
+		if !final_target_written && incomplete.contains(&FINAL_TARGET) {
+			steps.push(FrameStep::ClearFinalTarget);
+		}
+		steps
+	}
+}
+
+
+pub(crate) struct RenderGraphBuilder {
+	passes: Vec<Box<dyn RenderPass>>
+}
+
+impl RenderGraphBuilder {
+	pub fn add_pass(mut self, pass: impl RenderPass + 'static) -> Self {
+		self.passes.push(Box::new(pass));
+		self
+	}
+
+	/// Orders passes by their resource dependencies and culls passes
+	/// whose outputs are never used to produce [`FINAL_TARGET`].
+	/// Passes without declared writes are always kept.
+	/// Writers of the same resource run in the order they were added.
+	pub fn build(self) -> Result<RenderGraph, GraphError> {
+		let declarations = self.passes.iter()
+			.map(|pass| {
+				let mut resources = PassResources::default();
+				pass.declare(&mut resources);
+				resources
+			})
+			.collect::<Vec<_>>();
+
+		let mut names = HashSet::new();
+		for pass in &self.passes {
+			if !names.insert(pass.name()) {
+				return Err(GraphError::DuplicatePass(pass.name()));
+			}
+		}
+
+		let mut created = HashSet::new();
+		for declaration in &declarations {
+			let textures = declaration.textures.iter().map(|(name, _)| name);
+			let buffers = declaration.buffers.iter().map(|(name, _)| name);
+			for &resource in textures.chain(buffers) {
+				if !created.insert(resource) {
+					return Err(GraphError::DuplicateResource(resource));
+				}
+			}
+		}
+
+		let mut writers = HashMap::<&str, Vec<usize>>::new();
+		for (index, declaration) in declarations.iter().enumerate() {
+			for &resource in &declaration.writes {
+				writers.entry(resource).or_default().push(index);
+			}
+		}
+
+		let mut dependencies = vec![BTreeSet::new(); self.passes.len()];
+		for (index, declaration) in declarations.iter().enumerate() {
+			for &resource in &declaration.reads {
+				let producers = writers.get(resource)
+					.map(|writers| writers.iter().filter(|&&writer| writer != index))
+					.into_iter()
+					.flatten()
+					.copied()
+					.collect::<Vec<_>>();
+				if producers.is_empty() {
+					return Err(GraphError::MissingProducer {
+						pass: self.passes[index].name(),
+						resource
+					});
+				}
+				dependencies[index].extend(producers);
+			}
+			for resource in &declaration.writes {
+				dependencies[index].extend(writers[resource]
+					.iter()
+					.take_while(|&&writer| writer < index)
+				);
+			}
+		}
+
+		let live = live_passes(&declarations, &dependencies);
+		let order = sort_passes(&live, &dependencies)
+			.map_err(|cycle| GraphError::Cycle(cycle
+				.into_iter()
+				.map(|index| self.passes[index].name())
+				.collect()
+			))?;
+		let resources = self.plan_resources(&declarations, &order)?;
+
+		let mut passes = self.passes
+			.into_iter()
+			.zip(declarations)
+			.map(|(pass, declaration)| Some(GraphPass {
+				pass,
+				reads: declaration.reads,
+				writes: declaration.writes
+			}))
+			.collect::<Vec<_>>();
+		Ok(RenderGraph {
+			passes: order.into_iter()
+				.filter_map(|index| passes[index].take())
+				.collect(),
+			resources
+		})
+	}
+
+	/// Computes lifetimes of transient resources created by ordered passes
+	fn plan_resources(
+		&self,
+		declarations: &[PassResources],
+		order: &[usize]
+	) -> Result<ResourcePlan, GraphError> {
+		let mut textures = Vec::new();
+		let mut buffers = Vec::new();
+		let mut bind_groups = Vec::new();
+		let mut bind_group_names = HashSet::new();
+
+		for (position, &index) in order.iter().enumerate() {
+			let declaration = &declarations[index];
+			textures.extend(declaration.textures.iter().map(|&(name, desc)| {
+				TransientResource { name, desc, first_use: position, last_use: position }
+			}));
+			buffers.extend(declaration.buffers.iter().map(|&(name, desc)| {
+				TransientResource { name, desc, first_use: position, last_use: position }
+			}));
+
+			let used = || declaration.reads.iter().chain(&declaration.writes);
+			for resource in textures.iter_mut().filter(|r| used().any(|&u| u == r.name)) {
+				resource.last_use = position;
+			}
+			for resource in buffers.iter_mut().filter(|r| used().any(|&u| u == r.name)) {
+				resource.last_use = position;
+			}
+
+			for bind_group in &declaration.bind_groups {
+				if !bind_group_names.insert(bind_group.name) {
+					return Err(GraphError::DuplicateBindGroup(bind_group.name));
+				}
+				let invalid = bind_group.resources().find(|&resource| {
+					let transient = textures.iter().any(|r| r.name == resource)
+						|| buffers.iter().any(|r| r.name == resource);
+					!transient || !used().any(|&u| u == resource)
+				});
+				if let Some(resource) = invalid {
+					return Err(GraphError::InvalidBindGroupResource {
+						pass: self.passes[index].name(),
+						bind_group: bind_group.name,
+						resource
+					});
+				}
+				bind_groups.push(bind_group.clone());
+			}
+		}
+
+		let (textures, texture_slots) = assign_slots(textures);
+		let (buffers, buffer_slots) = assign_slots(buffers);
+		Ok(ResourcePlan { textures, texture_slots, buffers, buffer_slots, bind_groups })
+	}
+}
+
+/// Marks passes that contribute to the final target or have no outputs
+fn live_passes(
+	declarations: &[PassResources],
+	dependencies: &[BTreeSet<usize>]
+) -> Vec<bool> {
+	let mut live = vec![false; declarations.len()];
+	let mut stack = declarations.iter()
+		.enumerate()
+		.filter(|(_, declaration)| declaration.writes.is_empty()
+			|| declaration.writes.contains(&FINAL_TARGET)
+		)
+		.map(|(index, _)| index)
+		.collect::<Vec<_>>();
+
+	while let Some(index) = stack.pop() {
+		if !live[index] {
+			live[index] = true;
+			stack.extend(dependencies[index].iter().copied());
+		}
+	}
+	live
+}
+
+/// Topologically sorts live passes, ties are resolved by insertion order.
+/// Returns passes left unsorted if dependencies contain a cycle
+fn sort_passes(
+	live: &[bool],
+	dependencies: &[BTreeSet<usize>]
+) -> Result<Vec<usize>, Vec<usize>> {
+	let mut remaining = (0..live.len())
+		.filter(|&index| live[index])
+		.map(|index| (index, dependencies[index].len()))
+		.collect::<HashMap<_, _>>();
+	let mut ready = remaining.iter()
+		.filter(|&(_, &count)| count == 0)
+		.map(|(&index, _)| index)
+		.collect::<BTreeSet<_>>();
+	let mut order = Vec::with_capacity(remaining.len());
+
+	while let Some(index) = ready.pop_first() {
+		remaining.remove(&index);
+		order.push(index);
+		for (&dependent, count) in remaining.iter_mut() {
+			if dependencies[dependent].contains(&index) {
+				*count -= 1;
+				if *count == 0 {
+					ready.insert(dependent);
+				}
+			}
+		}
+	}
+
+	if remaining.is_empty() {
+		Ok(order)
+	}
+	else {
+		let mut cycle = remaining.into_keys().collect::<Vec<_>>();
+		cycle.sort_unstable();
+		Err(cycle)
+	}
+}
+
+
+#[derive(Debug)]
+pub enum GraphError {
+	/// Pipeline used by the pass is not a render asset
+	MissingPipeline {
+		pass: &'static str,
+		pipeline: &'static str
+	},
+	DuplicatePass(&'static str),
+	DuplicateResource(&'static str),
+	DuplicateBindGroup(&'static str),
+	MissingProducer {
+		pass: &'static str,
+		resource: &'static str
+	},
+	Cycle(Vec<&'static str>),
+	/// Bind group references resource that is not transient or not used by the pass
+	InvalidBindGroupResource {
+		pass: &'static str,
+		bind_group: &'static str,
+		resource: &'static str
+	}
+}
+
+impl fmt::Display for GraphError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::MissingPipeline { pass, pipeline } => {
+				write!(f, "Pipeline {} used by pass {} is not a render asset", pipeline, pass)
+			}
+			Self::DuplicatePass(pass) => write!(f, "Pass {} is added more than once", pass),
+			Self::DuplicateResource(resource) => {
+				write!(f, "Resource {} is created by more than one pass", resource)
+			}
+			Self::DuplicateBindGroup(bind_group) => {
+				write!(f, "Bind group {} is declared more than once", bind_group)
+			}
+			Self::MissingProducer { pass, resource } => {
+				write!(f, "Pass {} reads {} which is not written by any pass", pass, resource)
+			}
+			Self::Cycle(passes) => write!(f, "Passes {} form a dependency cycle", passes.join(", ")),
+			Self::InvalidBindGroupResource { pass, bind_group, resource } => write!(
+				f, "Bind group {} of pass {} uses {} which is not a transient resource used by the pass",
+				bind_group, pass, resource
+			)
+		}
+	}
+}
+
+impl error::Error for GraphError {}
+
+
+#[cfg(test)]
+mod tests {
+	use crate::resources::{TextureDesc, TextureFormat, TextureUsages};
+
+	use super::*;
+
+
+	/// Pass that only declares resources
+	#[derive(Default)]
+	struct TestPass {
+		name: &'static str,
+		creates: Vec<&'static str>,
+		reads: Vec<&'static str>,
+		writes: Vec<&'static str>
+	}
+
+	impl TestPass {
+		fn new(name: &'static str) -> Self {
+			Self { name, ..default() }
+		}
+
+		fn creates(mut self, resource: &'static str) -> Self {
+			self.creates.push(resource);
+			self
+		}
+
+		fn reads(mut self, resource: &'static str) -> Self {
+			self.reads.push(resource);
+			self
+		}
+
+		fn writes(mut self, resource: &'static str) -> Self {
+			self.writes.push(resource);
+			self
+		}
+	}
+
+	impl RenderPass for TestPass {
+		fn name(&self) -> &'static str { self.name }
+
+		fn declare(&self, resources: &mut PassResources) {
+			for &resource in &self.creates {
+				resources.create_texture(resource, TextureDesc::target_sized(
+					TextureFormat::Rgba8Unorm,
+					TextureUsages::TEXTURE_BINDING
+				));
+			}
+			for &resource in &self.reads {
+				resources.read(resource);
+			}
+			for &resource in &self.writes {
+				resources.write(resource);
+			}
+		}
+
+		fn run(
+			&self,
+			_frame: &mut FrameContext,
+			_capabilities: &GpuCapabilities,
+			_assets: &RenderAssets,
+			_resources: &RenderResources
+		) {
+			unreachable!()
+		}
+	}
+
+	fn pass_names(graph: &RenderGraph) -> Vec<&'static str> {
+		graph.passes.iter().map(|pass| pass.pass.name()).collect()
+	}
+
+	/// Passes run and final target clears of a frame where `skipped` passes aren't ready
+	fn frame_steps(graph: &RenderGraph, skipped: &[&str]) -> Vec<&'static str> {
+		graph.schedule(|pass| !skipped.contains(&pass.name()))
+			.into_iter()
+			.map(|step| match step {
+				FrameStep::Run(index) => graph.passes[index].pass.name(),
+				FrameStep::ClearFinalTarget => "clear"
+			})
+			.collect()
+	}
+
+	fn scene_blit_ui_graph() -> RenderGraph {
+		RenderGraph::builder()
+			.add_pass(TestPass::new("scene").creates("scene"))
+			.add_pass(TestPass::new("blit").reads("scene").writes(FINAL_TARGET))
+			.add_pass(TestPass::new("ui").writes(FINAL_TARGET))
+			.build()
+			.unwrap()
+	}
+
+	#[test]
+	fn ready_passes_run_without_clear() {
+		assert_eq!(frame_steps(&scene_blit_ui_graph(), &[]), ["scene", "blit", "ui"]);
+	}
+
+	#[test]
+	fn final_target_is_cleared_before_writer_following_skipped_writer() {
+		let graph = scene_blit_ui_graph();
+		assert_eq!(frame_steps(&graph, &["blit"]), ["scene", "clear", "ui"]);
+		// Readers of skipped passes are skipped too
+		assert_eq!(frame_steps(&graph, &["scene"]), ["clear", "ui"]);
+	}
+
+	#[test]
+	fn final_target_is_not_cleared_after_it_was_written() {
+		assert_eq!(frame_steps(&scene_blit_ui_graph(), &["ui"]), ["scene", "blit"]);
+	}
+
+	#[test]
+	fn final_target_is_cleared_if_no_writer_runs() {
+		assert_eq!(frame_steps(&scene_blit_ui_graph(), &["blit", "ui"]), ["scene", "clear"]);
+	}
+
+	#[test]
+	fn orders_readers_after_writers() {
+		let graph = RenderGraph::builder()
+			.add_pass(TestPass::new("blit").reads("lit").writes(FINAL_TARGET))
+			.add_pass(TestPass::new("lighting").reads("gbuffer").creates("lit"))
+			.add_pass(TestPass::new("gbuffer").creates("gbuffer"))
+			.build()
+			.unwrap();
+		assert_eq!(pass_names(&graph), ["gbuffer", "lighting", "blit"]);
+	}
+
+	#[test]
+	fn writers_of_same_resource_keep_insertion_order() {
+		let graph = RenderGraph::builder()
+			.add_pass(TestPass::new("blit").reads("scene").writes(FINAL_TARGET))
+			.add_pass(TestPass::new("ui").writes(FINAL_TARGET))
+			.add_pass(TestPass::new("scene").creates("scene"))
+			.build()
+			.unwrap();
+		assert_eq!(pass_names(&graph), ["scene", "blit", "ui"]);
+	}
+
+	#[test]
+	fn culls_passes_not_reaching_final_target() {
+		let graph = RenderGraph::builder()
+			.add_pass(TestPass::new("main").creates("output"))
+			.add_pass(TestPass::new("debug").reads("output").creates("debug_view"))
+			.add_pass(TestPass::new("blit").reads("output").writes(FINAL_TARGET))
+			// Passes without outputs are kept
+			.add_pass(TestPass::new("stats"))
+			.build()
+			.unwrap();
+		assert_eq!(pass_names(&graph), ["main", "blit", "stats"]);
+		assert!(!graph.resources().textures.contains_key("debug_view"));
+	}
+
+	#[test]
+	fn read_without_writer_is_missing_producer() {
+		let result = RenderGraph::builder()
+			.add_pass(TestPass::new("blit").reads("output").writes(FINAL_TARGET))
+			.build();
+		assert!(matches!(
+			result,
+			Err(GraphError::MissingProducer { pass: "blit", resource: "output" })
+		));
+	}
+
+	#[test]
+	fn reading_own_write_is_missing_producer() {
+		let result = RenderGraph::builder()
+			.add_pass(TestPass::new("feedback").reads("history").writes("history").writes(FINAL_TARGET))
+			.build();
+		assert!(matches!(
+			result,
+			Err(GraphError::MissingProducer { pass: "feedback", resource: "history" })
+		));
+	}
+
+	#[test]
+	fn mutual_reads_form_cycle() {
+		let result = RenderGraph::builder()
+			.add_pass(TestPass::new("blit").reads("a").writes(FINAL_TARGET))
+			.add_pass(TestPass::new("first").reads("b").writes("a"))
+			.add_pass(TestPass::new("second").reads("a").writes("b"))
+			.build();
+		// Passes depending on the cycle are reported too
+		match result {
+			Err(GraphError::Cycle(passes)) => assert_eq!(passes, ["blit", "first", "second"]),
+			_ => panic!("Expected cycle")
+		}
+	}
+
+	#[test]
+	fn sort_resolves_ties_by_insertion_order() {
+		let dependencies = [BTreeSet::new(), BTreeSet::from([3]), BTreeSet::new(), BTreeSet::new()];
+		assert_eq!(sort_passes(&[true; 4], &dependencies), Ok(vec![0, 2, 3, 1]));
+		assert_eq!(sort_passes(&[false, true, false, true], &dependencies), Ok(vec![3, 1]));
+	}
+}
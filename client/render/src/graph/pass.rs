@@ -0,0 +1,75 @@
+use crate::{
+	assets::RenderAssets,
+	core::{FrameContext, GpuCapabilities},
+	resources::{BindGroupDesc, BufferDesc, RenderResources, TextureDesc}
+};
+
+
+/// Resource written by the pass that produces the final frame
+pub(crate) const FINAL_TARGET: &str = "final_target";
+
+pub(crate) trait RenderPass {
+	fn name(&self) -> &'static str;
+
+	/// Declares resources that are read and written by the pass
+	fn declare(&self, resources: &mut PassResources);
+
+	/// Pass is skipped while it's not ready, e.g. its pipelines are compiling.
+	/// Passes reading resources written by skipped passes are skipped too
+	fn is_ready(&self, _assets: &RenderAssets) -> bool { true }
+
+	/// `capabilities` are the features and limits of the device passes record commands for
+	fn run(
+		&self,
+		frame: &mut FrameContext,
+		capabilities: &GpuCapabilities,
+		assets: &RenderAssets,
+		resources: &RenderResources
+	);
+}
+
+
+#[derive(Default)]
+pub(crate) struct PassResources {
+	pub(super) reads: Vec<&'static str>,
+	pub(super) writes: Vec<&'static str>,
+	pub(super) textures: Vec<(&'static str, TextureDesc)>,
+	pub(super) buffers: Vec<(&'static str, BufferDesc)>,
+	pub(super) bind_groups: Vec<BindGroupDesc>
+}
+
+impl PassResources {
+	pub fn read(&mut self, resource: &'static str) -> &mut Self {
+		self.reads.push(resource);
+		self
+	}
+
+	pub fn write(&mut self, resource: &'static str) -> &mut Self {
+		self.writes.push(resource);
+		self
+	}
+
+	/// Declares transient texture allocated by the graph and written by this pass.
+	/// Its contents are undefined before the first write since
+	/// it can share memory with other resources
+	pub fn create_texture(&mut self, resource: &'static str, desc: TextureDesc) -> &mut Self {
+		self.textures.push((resource, desc));
+		self.write(resource)
+	}
+
+	/// Declares transient buffer allocated by the graph and written by this pass.
+	/// Its contents are undefined before the first write since
+	/// it can share memory with other resources
+	#[allow(dead_code)]
+	pub fn create_buffer(&mut self, resource: &'static str, desc: BufferDesc) -> &mut Self {
+		self.buffers.push((resource, desc));
+		self.write(resource)
+	}
+
+	/// Declares bind group of transient resources,
+	/// resources must be read or written by this pass
+	pub fn bind_group(&mut self, desc: BindGroupDesc) -> &mut Self {
+		self.bind_groups.push(desc);
+		self
+	}
+}
@@ -1,9 +1,16 @@
+pub use capture::*;
 pub use config::*;
+pub use error::*;
+pub use graph::GraphError;
 pub use renderer::*;
+pub use resources::{BindGroupError, BindGroupErrorKind};
 
 pub mod assets;
+mod capture;
 mod core;
 mod config;
+mod error;
 mod graph;
+mod passes;
 mod renderer;
 mod resources;
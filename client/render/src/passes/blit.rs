@@ -0,0 +1,63 @@
+use default::default;
+
+use wgpu::{Color, RenderPassDescriptor, RenderPipeline};
+
+use crate::{
+	assets::{PipelineHandle, PipelineState, RenderAssets},
+	core::{FrameContext, GpuCapabilities},
+	graph::{GraphError, PassResources, RenderPass, FINAL_TARGET},
+	resources::{BindGroupDesc, RenderResources}
+};
+
+
+const NAME: &str = "blit";
+const PIPELINE: &str = "blit";
+
+pub(crate) struct BlitPass {
+	pipeline: PipelineHandle<RenderPipeline>
+}
+
+impl BlitPass {
+	pub fn new(assets: &RenderAssets) -> Result<Self, GraphError> {
+		let pipeline = assets.pipeline_handle(PIPELINE)
+			.ok_or(GraphError::MissingPipeline { pass: NAME, pipeline: PIPELINE })?;
+		Ok(Self { pipeline })
+	}
+}
+
+impl RenderPass for BlitPass {
+	fn name(&self) -> &'static str { NAME }
+
+	fn declare(&self, resources: &mut PassResources) {
+		resources
+			.read("output_texture")
+			.bind_group(BindGroupDesc::new("input_texture", "input_texture")
+				.texture(0, "output_texture"))
+			.write(FINAL_TARGET);
+	}
+
+	fn is_ready(&self, assets: &RenderAssets) -> bool {
+		assets.pipeline_state(&self.pipeline).is_ready()
+	}
+
+	fn run(
+		&self,
+		frame: &mut FrameContext,
+		_capabilities: &GpuCapabilities,
+		assets: &RenderAssets,
+		resources: &RenderResources
+	) {
+		let PipelineState::Ready(pipeline) = assets.pipeline_state(&self.pipeline) else {
+			return;
+		};
+		let attachment = frame.texture.clear_attachment(Color::BLACK);
+		let mut pass = frame.encoder.begin_render_pass(&RenderPassDescriptor {
+			label: Some("display"),
+			color_attachments: &[Some(attachment)],
+			..default()
+		});
+		pass.set_pipeline(pipeline);
+		pass.set_bind_group(0, resources.bind_group("input_texture"), &[]);
+		pass.draw(0..3, 0..1);
+	}
+}
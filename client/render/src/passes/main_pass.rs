@@ -0,0 +1,78 @@
+use log::warn;
+
+use wgpu::{ComputePassDescriptor, ComputePipeline};
+
+use crate::{
+	assets::{PipelineHandle, PipelineState, RenderAssets},
+	core::{FrameContext, GpuCapabilities},
+	graph::{GraphError, PassResources, RenderPass},
+	resources::{
+		BindGroupDesc, RenderResources, TextureDesc, TextureFormat, TextureUsages
+	}
+};
+
+
+const NAME: &str = "main_pass";
+const PIPELINE: &str = "main_pass";
+
+pub(crate) struct MainPass {
+	pipeline: PipelineHandle<ComputePipeline>
+}
+
+impl MainPass {
+	pub fn new(assets: &RenderAssets) -> Result<Self, GraphError> {
+		let pipeline = assets.pipeline_handle(PIPELINE)
+			.ok_or(GraphError::MissingPipeline { pass: NAME, pipeline: PIPELINE })?;
+		Ok(Self { pipeline })
+	}
+}
+
+impl RenderPass for MainPass {
+	fn name(&self) -> &'static str { NAME }
+
+	fn declare(&self, resources: &mut PassResources) {
+		resources
+			.create_texture("output_texture", TextureDesc::target_sized(
+				TextureFormat::Rgba8Unorm,
+				TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING
+			))
+			.bind_group(BindGroupDesc::new("output_texture", "output_texture")
+				.texture(0, "output_texture"));
+	}
+
+	/// Workgroup size is missing if it can't be reflected from the shader
+	fn is_ready(&self, assets: &RenderAssets) -> bool {
+		assets.pipeline_state(&self.pipeline).is_ready() && assets.workgroup_size(PIPELINE).is_some()
+	}
+
+	fn run(
+		&self,
+		frame: &mut FrameContext,
+		capabilities: &GpuCapabilities,
+		assets: &RenderAssets,
+		resources: &RenderResources
+	) {
+		// Workgroup size can change when the shader is reloaded
+		let (PipelineState::Ready(pipeline), Some([width, height, _])) =
+			(assets.pipeline_state(&self.pipeline), assets.workgroup_size(PIPELINE))
+		else {
+			return;
+		};
+		let workgroups = [
+			frame.texture.width().div_ceil(width),
+			frame.texture.height().div_ceil(height)
+		];
+		let max_workgroups = capabilities.limits.max_compute_workgroups_per_dimension;
+		if workgroups.iter().any(|&count| count > max_workgroups) {
+			warn!("Skipping {}, {:?} workgroups exceed device limit {}", NAME, workgroups, max_workgroups);
+			return;
+		}
+		let mut pass = frame.encoder.begin_compute_pass(&ComputePassDescriptor {
+			label: Some("main_pass"),
+			timestamp_writes: None,
+		});
+		pass.set_pipeline(pipeline);
+		pass.set_bind_group(0, resources.bind_group("output_texture"), &[]);
+		pass.dispatch_workgroups(workgroups[0], workgroups[1], 1);
+	}
+}
@@ -0,0 +1,19 @@
+pub(crate) use blit::*;
+pub(crate) use main_pass::*;
+
+mod blit;
+mod main_pass;
+
+
+use crate::{
+	assets::RenderAssets,
+	graph::{GraphError, RenderGraph}
+};
+
+
+pub(crate) fn create_render_graph(assets: &RenderAssets) -> Result<RenderGraph, GraphError> {
+	RenderGraph::builder()
+		.add_pass(MainPass::new(assets)?)
+		.add_pass(BlitPass::new(assets)?)
+		.build()
+}
@@ -1,66 +1,359 @@
+#[cfg(feature="hot-reload")]
+use std::path::Path;
+use std::io;
+
 use glued::module_impl;
+use log::{error, warn};
+
+pub use wgpu::TextureFormat;
+use wgpu::{Instance, SurfaceError};
+
+use starflow_util::{Mailbox, Size};
 
 use crate::{
-	assets::{create_render_assets, RenderAssets},
-	core::{util::SizedSurfaceTarget, FrameContext, GpuContext, RenderSurface},
+	assets::{
+		create_render_assets, extend_render_assets, AssetDependencies, HasRegistry,
+		PipelineCacheFile, RenderAsset, RenderAssets, RenderAssetsCreation, ShaderSources
+	},
+	core::{
+		util::SizedSurfaceTarget, FrameContext, GpuContext, OffscreenTarget, RenderSurface,
+		RenderTarget, TextureReadback
+	},
 	graph::RenderGraph,
+	passes::create_render_graph,
 	resources::RenderResources,
-	GpuContextConfig
+	CaptureError, CapturedFrame, Features, GpuContextConfig, InitError, Limits, RenderError
 };
 
 
 pub struct Renderer<'window> {
 	context: GpuContext,
-	surface: RenderSurface<'window>,
+	target: RenderTarget<'window>,
 	assets: RenderAssets,
+	shaders: ShaderSources,
 	resources: RenderResources,
-	graph: RenderGraph
+	graph: RenderGraph,
+	/// File the pipeline cache is loaded from and saved to
+	pipeline_cache_file: Option<PipelineCacheFile>,
+	resize_events: Option<Mailbox<Size<u32>>>,
+	/// Rendering is skipped while target has zero area
+	minimized: bool,
+	/// Rendering is stopped after fatal error
+	error: Option<RenderError>
 }
 
 impl<'w> Renderer<'w> {
 	pub async fn new(
 		config: GpuContextConfig<'_>,
 		surface_target: impl Into<SizedSurfaceTarget<'w>>
-	) -> Self {
-		let context = GpuContext::new(config).await;
-
+	) -> Result<Self, InitError> {
+		let instance = Instance::new(&config.instance_descriptor());
 		let target: SizedSurfaceTarget = surface_target.into();
+		let surface = instance
+			.create_surface(target.target)
+			.map_err(InitError::CreateSurface)?;
+
+		let context = GpuContext::new(instance, &config, Some(&surface)).await?;
 		let surface = RenderSurface::configured(
-			target.target, target.size, &context
-		).expect("Failed to create surface");
+			surface, target.size, target.source, &context
+		)?;
+
+		Self::with_target(context, RenderTarget::Surface(surface), &config)
+	}
+
+	/// Creates renderer without window, frames are rendered into offscreen texture
+	/// of given size and format
+	pub async fn new_headless(
+		config: GpuContextConfig<'_>,
+		size: Size<u32>,
+		format: TextureFormat
+	) -> Result<Self, InitError> {
+		let instance = Instance::new(&config.instance_descriptor());
+		let context = GpuContext::new(instance, &config, None).await?;
+		let target = OffscreenTarget::new(size, format, &context.device);
+
+		Self::with_target(context, RenderTarget::Offscreen(target), &config)
+	}
 
-		let assets = create_render_assets(&surface, &context.device);
+	fn with_target(
+		context: GpuContext,
+		target: RenderTarget<'w>,
+		config: &GpuContextConfig
+	) -> Result<Self, InitError> {
+		let shaders = ShaderSources::default();
+		let pipeline_cache_file = config.pipeline_cache_dir
+			.as_ref()
+			.and_then(|directory| PipelineCacheFile::new(directory, &context.adapter.get_info()));
+		let pipeline_cache = pipeline_cache_file
+			.as_ref()
+			.and_then(|file| file.load(&context.device));
+		let assets = create_render_assets(
+			target.texture_format(),
+			&context.device,
+			&shaders,
+			config.asset_manifest.clone(),
+			pipeline_cache,
+			config.pipeline_compilation
+		)?;
+		let graph = create_render_graph(&assets).map_err(InitError::Graph)?;
 		let resources = RenderResources::new(
 			&context.device,
 			&assets,
-			surface.size()
-		);
-		let graph = RenderGraph::new(&assets);
+			graph.resources(),
+			target.size()
+		).map_err(InitError::BindGroup)?;
 
-		Self {
+		Ok(Self {
 			context,
-			surface,
+			target,
 			assets,
+			shaders,
 			resources,
-			graph
+			graph,
+			pipeline_cache_file,
+			resize_events: None,
+			minimized: false,
+			error: None
+		})
+	}
+
+	/// Renderer will be resized to sizes received from `events` before drawing a frame
+	pub fn with_resize_events(mut self, events: Mailbox<Size<u32>>) -> Self {
+		self.resize_events = Some(events);
+		self
+	}
+
+	/// Dev mode, shaders are loaded from `directory` and pipelines are rebuilt when they change.
+	/// Asset manifest file is watched too if configured.
+	/// Embedded shaders are kept if the directory can't be watched
+	#[cfg(feature="hot-reload")]
+	pub fn with_shader_hot_reload(mut self, directory: impl AsRef<Path>) -> Self {
+		use crate::assets::{ShaderWatcher, WatchedChanges};
+
+		match ShaderWatcher::new(directory.as_ref(), self.assets.manifest_path()) {
+			Ok(watcher) => {
+				self.shaders = ShaderSources::Watched(watcher);
+				self.reload_assets(WatchedChanges {
+					shaders: ShaderSources::paths().map(Into::into).collect(),
+					manifest: false
+				});
+			}
+			Err(err) => error!("Failed to watch shader directory, using embedded shaders: {}", err)
 		}
+		self
 	}
 
-	fn draw_frame(&self) {
-		let encoder = self.context.create_encoder("main_encoder");
-		let swapchain_texture = self.surface
-			.get_swapchain_texture(&self.context.device)
-			.expect("Failed to obtain texture");
-		let mut frame = FrameContext::new(
-			encoder,
-			swapchain_texture
+	#[cfg(feature="hot-reload")]
+	fn reload_changed_shaders(&mut self) {
+		let ShaderSources::Watched(watcher) = &self.shaders else {
+			return;
+		};
+		let changes = watcher.changes();
+		if changes.manifest || !changes.shaders.is_empty() {
+			self.reload_assets(changes);
+		}
+	}
+
+	#[cfg(feature="hot-reload")]
+	fn reload_assets(&mut self, changes: crate::assets::WatchedChanges) {
+		crate::assets::reload_render_assets(
+			&mut self.assets,
+			self.target.texture_format(),
+			&self.context.device,
+			&self.shaders,
+			changes.shaders,
+			changes.manifest
+		);
+		self.resources.update_bind_groups(&self.context.device, &self.assets, self.graph.resources());
+	}
+
+	/// Creates render assets in addition to those of the manifest.
+	/// Replacing an asset recreates the manifest assets built from it
+	pub fn create_assets<R>(&mut self, create: impl FnOnce(&mut RenderAssetsCreation) -> R) -> R {
+		let result = extend_render_assets(
+			&mut self.assets,
+			self.target.texture_format(),
+			&self.context.device,
+			&self.shaders,
+			create
+		);
+		self.resources.update_bind_groups(&self.context.device, &self.assets, self.graph.resources());
+		result
+	}
+
+	/// Render asset with given key, `None` while a pipeline is compiling
+	#[allow(private_bounds)]
+	pub fn asset<R>(&self, key: &str) -> Option<&R>
+	where
+		R: RenderAsset,
+		RenderAssets: HasRegistry<R>
+	{
+		self.assets.get_asset(key)
+	}
+
+	/// Resizes the final target and recreates size dependent resources and bind groups.
+	/// Zero sized target (e.g. minimized window) is not rendered until resized again
+	pub fn resize(&mut self, size: Size<u32>) {
+		self.minimized = size.width == 0 || size.height == 0;
+		if self.minimized || size == self.target.size() {
+			return;
+		}
+		self.target.resize(size, &self.context.device);
+		self.resources.allocate(
+			&self.context.device,
+			&self.assets,
+			self.graph.resources(),
+			self.target.size()
 		);
+	}
+
+	fn receive_resize(&mut self) {
+		let size = self.resize_events
+			.as_ref()
+			.and_then(Mailbox::receive);
+		if let Some(size) = size {
+			self.resize(size);
+		}
+	}
+
+	/// Features enabled on the device, including supported optional features
+	pub fn features(&self) -> Features {
+		self.context.capabilities.features
+	}
+
+	/// Limits negotiated with the adapter
+	pub fn limits(&self) -> Limits {
+		self.context.capabilities.limits.clone()
+	}
+
+	/// Assets each render asset was created from
+	pub fn asset_dependencies(&self) -> &AssetDependencies {
+		self.assets.dependencies()
+	}
+
+	/// Number of pipelines being compiled asynchronously,
+	/// passes using them are skipped until they are ready
+	pub fn pending_pipelines(&self) -> usize {
+		self.assets.pending_pipelines()
+	}
+
+	/// Blocks until all asynchronously compiled pipelines are ready or failed
+	pub fn wait_for_pipelines(&mut self) {
+		self.assets.wait_for_pipelines();
+	}
+
+	/// Writes compiled pipelines to the pipeline cache file, also done when renderer is dropped.
+	/// Does nothing if pipeline cache is not configured or not supported by adapter
+	pub fn save_pipeline_cache(&self) -> io::Result<()> {
+		match (&self.pipeline_cache_file, self.assets.pipeline_cache()) {
+			(Some(file), Some(cache)) => file.save(cache),
+			_ => Ok(())
+		}
+	}
+
+	/// Fatal error that stopped rendering
+	pub fn error(&self) -> Option<&RenderError> {
+		self.error.as_ref()
+	}
+
+	/// Frame is skipped on recoverable surface errors,
+	/// fatal errors are returned
+	pub fn draw_frame(&mut self) -> Result<(), RenderError> {
+		if self.minimized {
+			return Ok(());
+		}
+		let mut frame = match self.begin_frame() {
+			Ok(frame) => frame,
+			Err(SurfaceError::OutOfMemory) => return Err(RenderError::OutOfMemory),
+			Err(SurfaceError::Lost) => return Err(RenderError::SurfaceLost),
+			Err(SurfaceError::Timeout) => {
+				warn!("Timed out acquiring frame texture, skipping frame");
+				return Ok(());
+			}
+			Err(err) => {
+				error!("Failed to acquire frame texture, skipping frame: {}", err);
+				return Ok(());
+			}
+		};
 		self.graph.run(
 			&mut frame,
+			&self.context.capabilities,
 			&self.assets,
 			&self.resources
 		);
 		frame.finish(&self.context.queue);
+		Ok(())
+	}
+
+	/// Renders a frame and reads back the final color target
+	pub fn capture_frame(&mut self) -> Result<CapturedFrame, CaptureError> {
+		self.capture(None)
+	}
+
+	/// Renders a frame and reads back the render resource texture with given name.
+	/// Fails for transient textures whose memory is reused later in the frame
+	pub fn capture_texture(&mut self, name: &str) -> Result<CapturedFrame, CaptureError> {
+		self.capture(Some(name))
+	}
+
+	fn capture(&mut self, texture_name: Option<&str>) -> Result<CapturedFrame, CaptureError> {
+		if let Some(name) = texture_name {
+			if self.resources.get_texture(name).is_none() {
+				return Err(CaptureError::MissingTexture(name.into()));
+			}
+			if self.graph.resources().is_texture_aliased_later(name) {
+				return Err(CaptureError::AliasedTexture(name.into()));
+			}
+		}
+
+		let mut frame = self.begin_frame()?;
+		self.graph.run(
+			&mut frame,
+			&self.context.capabilities,
+			&self.assets,
+			&self.resources
+		);
+		let texture = texture_name
+			.and_then(|name| self.resources.get_texture(name))
+			.unwrap_or_else(|| frame.texture.texture());
+		let readback = TextureReadback::encode(
+			&self.context.device,
+			&mut frame.encoder,
+			texture
+		)?;
+		frame.finish(&self.context.queue);
+		readback.read(&self.context.device)
+	}
+
+	/// Recreates lost surface once before giving up.
+	/// Pipelines compiled since the previous frame are used from this frame
+	fn begin_frame(&mut self) -> Result<FrameContext, SurfaceError> {
+		self.assets.receive_compiled_pipelines();
+		let encoder = self.context.create_encoder("main_encoder");
+		let target_texture = match self.target.get_target_texture(&self.context.device) {
+			Err(SurfaceError::Lost) => {
+				warn!("Surface lost, recreating");
+				self.target.recreate(&self.context);
+				self.target.get_target_texture(&self.context.device)?
+			}
+			result => result?
+		};
+		Ok(FrameContext::new(
+			encoder,
+			target_texture
+		))
+	}
+}
+
+impl Drop for Renderer<'_> {
+	fn drop(&mut self) {
+		// Pipelines still compiling are waited for so that they are saved too
+		if self.pipeline_cache_file.is_some() && self.assets.pipeline_cache().is_some() {
+			self.assets.wait_for_pipelines();
+		}
+		if let Err(err) = self.save_pipeline_cache() {
+			error!("Failed to save pipeline cache: {}", err);
+		}
 	}
 }
 
@@ -70,6 +363,16 @@ impl<'w> Renderer<'w> {
 impl Renderer<'_> {
 	#[inline(always)]
 	pub fn update(app: &mut A) {
-		app.module::<Self>().draw_frame();
+		let renderer = app.module::<Self>();
+		if renderer.error.is_some() {
+			return;
+		}
+		#[cfg(feature="hot-reload")]
+		renderer.reload_changed_shaders();
+		renderer.receive_resize();
+		if let Err(err) = renderer.draw_frame() {
+			error!("Rendering stopped: {}", err);
+			renderer.error = Some(err);
+		}
 	}
 }
@@ -0,0 +1,479 @@
+use core::{error, fmt, num::NonZero};
+
+use wgpu::{
+	BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry,
+	BindingResource, BindingType, Buffer, BufferBinding, BufferBindingType, BufferUsages, Device,
+	TextureSampleType, TextureUsages, TextureView, TextureViewDimension
+};
+
+use crate::{assets::RenderAssets, core::util::AsBindGroupEntry};
+
+use super::{BindGroupDesc, BindGroupResource, RenderResources};
+
+
+/// Bind group with the layout and resources it was created from
+pub(super) struct CachedBindGroup {
+	pub bind_group: BindGroup,
+	layout: BindGroupLayout,
+	resources: Vec<BoundResource>
+}
+
+#[derive(PartialEq)]
+enum BoundResource {
+	Texture(TextureView),
+	Buffer(Buffer)
+}
+
+/// Physical resources bound to an entry
+enum ResolvedResource<'r> {
+	Texture(&'r TextureView),
+	TextureArray(Vec<&'r TextureView>),
+	Buffer(BufferBinding<'r>)
+}
+
+impl RenderResources {
+	/// Creates bind group unless neither layout nor bound resources
+	/// of the previous one were recreated
+	pub(super) fn update_bind_group(
+		&self,
+		device: &Device,
+		assets: &RenderAssets,
+		desc: &BindGroupDesc,
+		previous: Option<&CachedBindGroup>
+	) -> Result<Option<CachedBindGroup>, BindGroupError> {
+		let error = |kind| BindGroupError { bind_group: desc.name, kind };
+		let layout = assets.get_asset::<BindGroupLayout>(desc.layout)
+			.ok_or_else(|| error(BindGroupErrorKind::MissingLayout(desc.layout)))?;
+
+		let resources = desc.resources()
+			.map(|name| self.bound_resource(name).map_err(error))
+			.collect::<Result<Vec<_>, _>>()?;
+		if let Some(previous) = previous
+			&& previous.layout == *layout
+			&& previous.resources == resources
+		{
+			return Ok(None);
+		}
+
+		for (index, (binding, _)) in desc.entries.iter().enumerate() {
+			if desc.entries[..index].iter().any(|(previous, _)| previous == binding) {
+				return Err(error(BindGroupErrorKind::DuplicateBinding(*binding)));
+			}
+		}
+		let layout_entries = assets.layout_entries(desc.layout).unwrap_or_default();
+		let resolved = desc.entries.iter()
+			.map(|(binding, resource)| {
+				let layout_entry = layout_entries.iter().find(|entry| entry.binding == *binding);
+				self.validate_entry(*binding, resource, layout_entry).map_err(error)?;
+				Ok((*binding, self.resolve(resource)))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		if let Some(unbound) = layout_entries.iter()
+			.find(|entry| !desc.entries.iter().any(|(binding, _)| *binding == entry.binding))
+		{
+			return Err(error(BindGroupErrorKind::UnboundEntry(unbound.binding)));
+		}
+
+		let entries = resolved.iter()
+			.map(|(binding, resource)| match resource {
+				ResolvedResource::Texture(view) => view.as_bind_group_entry(*binding),
+				ResolvedResource::TextureArray(views) => views.as_bind_group_entry(*binding),
+				ResolvedResource::Buffer(buffer) => BindGroupEntry {
+					binding: *binding,
+					resource: BindingResource::Buffer(buffer.clone())
+				}
+			})
+			.collect::<Vec<_>>();
+		let bind_group = device.create_bind_group(&BindGroupDescriptor {
+			label: Some(desc.name),
+			layout,
+			entries: &entries
+		});
+		Ok(Some(CachedBindGroup { bind_group, layout: layout.clone(), resources }))
+	}
+
+	fn bound_resource(&self, name: &'static str) -> Result<BoundResource, BindGroupErrorKind> {
+		if let Some(&slot) = self.textures.get(name) {
+			return Ok(BoundResource::Texture(self.texture_slots[slot].view.clone()));
+		}
+		match self.buffers.get(name) {
+			Some(&slot) => Ok(BoundResource::Buffer(self.buffer_slots[slot].buffer.clone())),
+			None => Err(BindGroupErrorKind::MissingResource(name))
+		}
+	}
+
+	fn resolve(&self, resource: &BindGroupResource) -> ResolvedResource<'_> {
+		match resource {
+			BindGroupResource::Texture(name) => {
+				ResolvedResource::Texture(&self.texture_slots[self.textures[name]].view)
+			}
+			BindGroupResource::TextureArray(names) => {
+				let views = names.iter()
+					.map(|name| &self.texture_slots[self.textures[name]].view)
+					.collect();
+				ResolvedResource::TextureArray(views)
+			}
+			BindGroupResource::Buffer { name, offset, size } => {
+				ResolvedResource::Buffer(BufferBinding {
+					buffer: &self.buffer_slots[self.buffers[name]].buffer,
+					offset: *offset,
+					size: *size
+				})
+			}
+		}
+	}
+
+	/// Checks that resource can be bound to layout entry
+	fn validate_entry(
+		&self,
+		binding: u32,
+		resource: &BindGroupResource,
+		layout_entry: Option<&BindGroupLayoutEntry>
+	) -> Result<(), BindGroupErrorKind> {
+		let Some(layout_entry) = layout_entry else {
+			return Err(BindGroupErrorKind::UnknownBinding(binding));
+		};
+		let (names, array) = match resource {
+			BindGroupResource::Texture(name) => (core::slice::from_ref(name), false),
+			BindGroupResource::TextureArray(names) => (names.as_slice(), true),
+			BindGroupResource::Buffer { name, offset, size } => {
+				if layout_entry.count.is_some() {
+					return Err(BindGroupErrorKind::ArrayLength {
+						binding, expected: layout_entry.count, found: None
+					});
+				}
+				return self.validate_buffer(binding, name, *offset, *size, layout_entry.ty);
+			}
+		};
+
+		let found = array.then_some(names.len());
+		if layout_entry.count.map(NonZero::get).map(|count| count as usize) != found {
+			return Err(BindGroupErrorKind::ArrayLength {
+				binding, expected: layout_entry.count, found
+			});
+		}
+		for &name in names {
+			// Bound resources exist, so a missing texture is a buffer
+			let Some(&slot) = self.textures.get(name) else {
+				return Err(BindGroupErrorKind::IncompatibleResource {
+					binding, resource: name, expected: layout_entry.ty
+				});
+			};
+			let texture = &self.texture_slots[slot].key;
+			let compatible = match layout_entry.ty {
+				BindingType::Texture { sample_type, view_dimension, multisampled } => {
+					texture.usage.contains(TextureUsages::TEXTURE_BINDING)
+						&& view_dimension == TextureViewDimension::D2
+						&& !multisampled
+						&& texture.format.sample_type(None, None)
+							.is_some_and(|format| is_sample_type_compatible(sample_type, format))
+				}
+				BindingType::StorageTexture { format, view_dimension, .. } => {
+					texture.usage.contains(TextureUsages::STORAGE_BINDING)
+						&& view_dimension == TextureViewDimension::D2
+						&& texture.format == format
+				}
+				_ => false
+			};
+			if !compatible {
+				return Err(BindGroupErrorKind::IncompatibleResource {
+					binding, resource: name, expected: layout_entry.ty
+				});
+			}
+		}
+		Ok(())
+	}
+
+	fn validate_buffer(
+		&self,
+		binding: u32,
+		name: &'static str,
+		offset: u64,
+		size: Option<NonZero<u64>>,
+		ty: BindingType
+	) -> Result<(), BindGroupErrorKind> {
+		// Bound resources exist, so a missing buffer is a texture
+		let Some(&slot) = self.buffers.get(name) else {
+			return Err(BindGroupErrorKind::IncompatibleResource { binding, resource: name, expected: ty });
+		};
+		let desc = self.buffer_slots[slot].desc;
+		let bound_size = match size {
+			Some(size) => size.get(),
+			None => desc.size.saturating_sub(offset)
+		};
+		if offset.checked_add(bound_size).is_none_or(|end| end > desc.size) || bound_size == 0 {
+			return Err(BindGroupErrorKind::OutOfBounds { binding, resource: name });
+		}
+		let compatible = match ty {
+			BindingType::Buffer { ty, min_binding_size, .. } => {
+				let usage = match ty {
+					BufferBindingType::Uniform => BufferUsages::UNIFORM,
+					BufferBindingType::Storage { .. } => BufferUsages::STORAGE
+				};
+				desc.usage.contains(usage)
+					&& min_binding_size.is_none_or(|min_size| bound_size >= min_size.get())
+			}
+			_ => false
+		};
+		if !compatible {
+			return Err(BindGroupErrorKind::IncompatibleResource {
+				binding, resource: name, expected: ty
+			});
+		}
+		Ok(())
+	}
+}
+
+/// Unfilterable float layout accepts any float texture
+fn is_sample_type_compatible(layout: TextureSampleType, format: TextureSampleType) -> bool {
+	match (layout, format) {
+		(TextureSampleType::Float { filterable: false }, TextureSampleType::Float { .. }) => true,
+		(TextureSampleType::Float { filterable: false }, TextureSampleType::Depth) => true,
+		(layout, format) => layout == format
+	}
+}
+
+
+#[derive(Debug)]
+pub struct BindGroupError {
+	pub bind_group: &'static str,
+	pub kind: BindGroupErrorKind
+}
+
+#[derive(Debug)]
+pub enum BindGroupErrorKind {
+	MissingLayout(&'static str),
+	/// Bound resource is not allocated by the render graph
+	MissingResource(&'static str),
+	DuplicateBinding(u32),
+	/// Layout has no entry for the binding
+	UnknownBinding(u32),
+	/// Layout entry has no resource bound
+	UnboundEntry(u32),
+	IncompatibleResource {
+		binding: u32,
+		resource: &'static str,
+		expected: BindingType
+	},
+	/// Array bindings must bind exactly `count` resources, `None` is a single resource
+	ArrayLength {
+		binding: u32,
+		expected: Option<NonZero<u32>>,
+		found: Option<usize>
+	},
+	/// Bound range exceeds buffer size
+	OutOfBounds {
+		binding: u32,
+		resource: &'static str
+	}
+}
+
+impl fmt::Display for BindGroupError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Failed to create bind group {}: ", self.bind_group)?;
+		match &self.kind {
+			BindGroupErrorKind::MissingLayout(layout) => {
+				write!(f, "missing bind group layout {}", layout)
+			}
+			BindGroupErrorKind::MissingResource(resource) => {
+				write!(f, "missing resource {}", resource)
+			}
+			BindGroupErrorKind::DuplicateBinding(binding) => {
+				write!(f, "binding {} is bound more than once", binding)
+			}
+			BindGroupErrorKind::UnknownBinding(binding) => {
+				write!(f, "binding {} is not in layout", binding)
+			}
+			BindGroupErrorKind::UnboundEntry(binding) => {
+				write!(f, "binding {} has no resource bound", binding)
+			}
+			BindGroupErrorKind::IncompatibleResource { binding, resource, expected } => write!(
+				f, "{} can't be bound to binding {} of type {:?}", resource, binding, expected
+			),
+			BindGroupErrorKind::ArrayLength { binding, expected, found } => write!(
+				f, "binding {} expects {}, found {}",
+				binding,
+				ArrayLength(expected.map(|count| count.get() as usize)),
+				ArrayLength(*found)
+			),
+			BindGroupErrorKind::OutOfBounds { binding, resource } => {
+				write!(f, "bound range of {} at binding {} is out of bounds", resource, binding)
+			}
+		}
+	}
+}
+
+struct ArrayLength(Option<usize>);
+
+impl fmt::Display for ArrayLength {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.0 {
+			Some(length) => write!(f, "array of {}", length),
+			None => write!(f, "single resource")
+		}
+	}
+}
+
+impl error::Error for BindGroupError {}
+
+
+#[cfg(test)]
+mod tests {
+	use starflow_util::Size;
+	use wgpu::{ShaderStages, TextureFormat};
+
+	use crate::{
+		assets::{desc, util::binding, RenderAssetsCreation, ShaderSources},
+		core::GpuContext,
+		resources::{assign_slots, BufferDesc, ResourcePlan, TextureDesc, TransientResource}
+	};
+
+	use super::*;
+
+
+	const SIZE: Size<u32> = Size { width: 4, height: 4 };
+
+	/// Target sized `sampled` and `storage` textures, `uniform` and `storage_buffer` buffers
+	/// of 64 bytes, all used by a single pass
+	fn plan(bind_groups: Vec<BindGroupDesc>) -> ResourcePlan {
+		let texture = |name, usage| TransientResource {
+			name,
+			desc: TextureDesc::target_sized(TextureFormat::Rgba8Unorm, usage),
+			first_use: 0,
+			last_use: 0
+		};
+		let buffer = |name, usage| TransientResource {
+			name, desc: BufferDesc { size: 64, usage }, first_use: 0, last_use: 0
+		};
+		let (textures, texture_slots) = assign_slots(vec![
+			texture("sampled", TextureUsages::TEXTURE_BINDING),
+			texture("storage", TextureUsages::STORAGE_BINDING)
+		]);
+		let (buffers, buffer_slots) = assign_slots(vec![
+			buffer("uniform", BufferUsages::UNIFORM),
+			buffer("storage_buffer", BufferUsages::STORAGE)
+		]);
+		ResourcePlan { textures, texture_slots, buffers, buffer_slots, bind_groups }
+	}
+
+	fn layout_assets(gpu: &GpuContext, entries: &[BindGroupLayoutEntry]) -> RenderAssets {
+		let mut assets = RenderAssets::default();
+		let shaders = ShaderSources::Embedded;
+		let format = TextureFormat::Rgba8Unorm;
+		RenderAssetsCreation::new(&mut assets, format, &gpu.device, &shaders)
+			.create(desc::BindGroupLayout::new("layout", entries))
+			.unwrap();
+		assets
+	}
+
+	/// Error of creating bind group described by `desc` with layout of `entries`
+	fn bind_group_error(
+		entries: &[BindGroupLayoutEntry],
+		desc: BindGroupDesc
+	) -> Option<BindGroupErrorKind> {
+		let gpu = GpuContext::for_tests()?;
+		let assets = layout_assets(&gpu, entries);
+		let mut resources = RenderResources::default();
+		resources.allocate_resources(&gpu.device, &plan(Vec::new()), SIZE);
+		match resources.update_bind_group(&gpu.device, &assets, &desc, None) {
+			Ok(_) => panic!("Bind group {} was created", desc.name),
+			Err(err) => Some(err.kind)
+		}
+	}
+
+	fn uniform(index: u32) -> BindGroupLayoutEntry {
+		binding(index).visibility(ShaderStages::COMPUTE).uniform_buffer()
+	}
+
+	fn sampled(index: u32) -> BindGroupLayoutEntry {
+		binding(index)
+			.visibility(ShaderStages::COMPUTE)
+			.texture_2d(TextureSampleType::Float { filterable: true })
+	}
+
+
+	#[test]
+	fn incompatible_resource_is_rejected() {
+		let desc = BindGroupDesc::new("bind_group", "layout").buffer(0, "storage_buffer");
+		let Some(kind) = bind_group_error(&[uniform(0)], desc) else { return };
+		assert!(matches!(kind, BindGroupErrorKind::IncompatibleResource {
+			binding: 0, resource: "storage_buffer", ..
+		}));
+
+		let desc = BindGroupDesc::new("bind_group", "layout").texture(0, "storage");
+		let Some(kind) = bind_group_error(&[sampled(0)], desc) else { return };
+		assert!(matches!(kind, BindGroupErrorKind::IncompatibleResource {
+			binding: 0, resource: "storage", ..
+		}));
+
+		let desc = BindGroupDesc::new("bind_group", "layout").texture(0, "sampled");
+		let Some(kind) = bind_group_error(&[uniform(0)], desc) else { return };
+		assert!(matches!(kind, BindGroupErrorKind::IncompatibleResource {
+			binding: 0, resource: "sampled", ..
+		}));
+	}
+
+	#[test]
+	fn array_length_must_match_count() {
+		let desc = BindGroupDesc::new("bind_group", "layout").texture_array(0, &["sampled"]);
+		let Some(kind) = bind_group_error(&[sampled(0)], desc) else { return };
+		assert!(matches!(kind, BindGroupErrorKind::ArrayLength {
+			binding: 0, expected: None, found: Some(1)
+		}));
+	}
+
+	#[test]
+	fn buffer_range_out_of_bounds_is_rejected() {
+		let desc = BindGroupDesc::new("bind_group", "layout").buffer_slice(0, "uniform", 32..128);
+		let Some(kind) = bind_group_error(&[uniform(0)], desc) else { return };
+		assert!(matches!(kind, BindGroupErrorKind::OutOfBounds { binding: 0, resource: "uniform" }));
+
+		let desc = BindGroupDesc::new("bind_group", "layout").buffer_slice(0, "uniform", 64..80);
+		let Some(kind) = bind_group_error(&[uniform(0)], desc) else { return };
+		assert!(matches!(kind, BindGroupErrorKind::OutOfBounds { binding: 0, resource: "uniform" }));
+	}
+
+	#[test]
+	fn unbound_layout_entry_is_rejected() {
+		let desc = BindGroupDesc::new("bind_group", "layout").buffer(0, "uniform");
+		let Some(kind) = bind_group_error(&[uniform(0), sampled(1)], desc) else { return };
+		assert!(matches!(kind, BindGroupErrorKind::UnboundEntry(1)));
+	}
+
+	#[test]
+	fn duplicate_binding_is_rejected() {
+		let desc = BindGroupDesc::new("bind_group", "layout")
+			.buffer(0, "uniform")
+			.buffer(0, "uniform");
+		let Some(kind) = bind_group_error(&[uniform(0)], desc) else { return };
+		assert!(matches!(kind, BindGroupErrorKind::DuplicateBinding(0)));
+	}
+
+	#[test]
+	fn size_dependent_bind_groups_are_recreated_on_resize() {
+		let Some(gpu) = GpuContext::for_tests() else { return };
+		let assets = layout_assets(&gpu, &[sampled(0), uniform(1)]);
+		let plan = plan(vec![
+			BindGroupDesc::new("bind_group", "layout")
+				.texture(0, "sampled")
+				.buffer(1, "uniform")
+		]);
+		let mut resources = RenderResources::new(&gpu.device, &assets, &plan, SIZE).unwrap();
+		let bind_group = resources.bind_group("bind_group").clone();
+
+		resources.allocate(&gpu.device, &assets, &plan, SIZE);
+		assert_eq!(resources.bind_group("bind_group"), &bind_group);
+
+		resources.allocate(&gpu.device, &assets, &plan, Size::new(8, 8));
+		assert_ne!(resources.bind_group("bind_group"), &bind_group);
+	}
+
+	#[test]
+	fn unknown_resource_is_missing() {
+		let resources = RenderResources::default();
+		assert!(matches!(
+			resources.bound_resource("unknown"),
+			Err(BindGroupErrorKind::MissingResource("unknown"))
+		));
+	}
+}
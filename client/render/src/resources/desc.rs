@@ -0,0 +1,117 @@
+use core::{num::NonZero, ops::Range};
+
+pub use wgpu::{BufferUsages, TextureFormat, TextureUsages};
+
+use starflow_util::Size;
+
+
+/// Size of a transient texture
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceSize {
+	Absolute(Size<u32>),
+	/// Size of the final target multiplied by scale
+	Relative(f32)
+}
+
+impl ResourceSize {
+	pub fn resolve(&self, target_size: Size<u32>) -> Size<u32> {
+		match *self {
+			Self::Absolute(size) => size,
+			Self::Relative(scale) => Size::new(
+				((target_size.width as f32 * scale).round() as u32).max(1),
+				((target_size.height as f32 * scale).round() as u32).max(1)
+			)
+		}
+	}
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureDesc {
+	pub size: ResourceSize,
+	pub format: TextureFormat,
+	pub usage: TextureUsages
+}
+
+impl TextureDesc {
+	/// Texture of the same size as the final target
+	pub fn target_sized(format: TextureFormat, usage: TextureUsages) -> Self {
+		Self { size: ResourceSize::Relative(1.0), format, usage }
+	}
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferDesc {
+	pub size: u64,
+	pub usage: BufferUsages
+}
+
+
+/// Bind group of transient resources, validated against its layout on creation
+/// and recreated when the layout or any bound resource is recreated
+#[derive(Clone, Debug)]
+pub(crate) struct BindGroupDesc {
+	pub name: &'static str,
+	pub layout: &'static str,
+	pub entries: Vec<(u32, BindGroupResource)>
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum BindGroupResource {
+	Texture(&'static str),
+	/// Bound to `binding_array` entry
+	TextureArray(Vec<&'static str>),
+	Buffer {
+		name: &'static str,
+		offset: u64,
+		/// Rest of the buffer if `None`
+		size: Option<NonZero<u64>>
+	}
+}
+
+#[allow(dead_code)]
+impl BindGroupDesc {
+	pub fn new(name: &'static str, layout: &'static str) -> Self {
+		Self { name, layout, entries: Vec::new() }
+	}
+
+	pub fn texture(mut self, binding: u32, resource: &'static str) -> Self {
+		self.entries.push((binding, BindGroupResource::Texture(resource)));
+		self
+	}
+
+	pub fn texture_array(mut self, binding: u32, resources: &[&'static str]) -> Self {
+		self.entries.push((binding, BindGroupResource::TextureArray(resources.to_vec())));
+		self
+	}
+
+	pub fn buffer(mut self, binding: u32, resource: &'static str) -> Self {
+		self.entries.push((binding, BindGroupResource::Buffer { name: resource, offset: 0, size: None }));
+		self
+	}
+
+	/// # Panics
+	/// If range is empty
+	pub fn buffer_slice(mut self, binding: u32, resource: &'static str, range: Range<u64>) -> Self {
+		let size = NonZero::new(range.end.saturating_sub(range.start))
+			.expect("Bound buffer slice is empty");
+		self.entries.push((binding, BindGroupResource::Buffer {
+			name: resource,
+			offset: range.start,
+			size: Some(size)
+		}));
+		self
+	}
+
+	/// Names of all bound resources
+	pub fn resources(&self) -> impl Iterator<Item = &'static str> + '_ {
+		self.entries.iter().flat_map(|(_, resource)| match resource {
+			BindGroupResource::Texture(name) | BindGroupResource::Buffer { name, .. } => {
+				core::slice::from_ref(name)
+			}
+			BindGroupResource::TextureArray(names) => names.as_slice()
+		}).copied()
+	}
+}
@@ -0,0 +1,181 @@
+pub use bind_groups::*;
+pub use desc::*;
+pub(crate) use plan::*;
+
+mod bind_groups;
+mod desc;
+mod plan;
+
+
+use std::{collections::HashMap, mem};
+
+use default::default;
+use log::error;
+
+use wgpu::{
+	BindGroup, Buffer, BufferDescriptor, Device, Texture, TextureDescriptor, TextureDimension,
+	TextureView
+};
+
+use starflow_util::Size;
+
+use crate::assets::RenderAssets;
+
+
+/// Physical resources backing transient resources of the render graph.
+/// Physical textures and buffers are pooled and reused on reallocation
+/// when their resolved descriptors match
+#[derive(Default)]
+pub(crate) struct RenderResources {
+	textures: HashMap<&'static str, usize>,
+	texture_slots: Vec<PooledTexture>,
+	buffers: HashMap<&'static str, usize>,
+	buffer_slots: Vec<PooledBuffer>,
+	bind_groups: HashMap<&'static str, CachedBindGroup>
+}
+
+impl RenderResources {
+	pub fn new(
+		device: &Device,
+		assets: &RenderAssets,
+		plan: &ResourcePlan,
+		target_size: Size<u32>
+	) -> Result<Self, BindGroupError> {
+		let mut resources = Self::default();
+		resources.allocate_resources(device, plan, target_size);
+		for desc in &plan.bind_groups {
+			if let Some(bind_group) = resources.update_bind_group(device, assets, desc, None)? {
+				resources.bind_groups.insert(desc.name, bind_group);
+			}
+		}
+		Ok(resources)
+	}
+
+	/// (Re)creates resources for the plan and target size
+	/// and bind groups of recreated resources
+	pub fn allocate(
+		&mut self,
+		device: &Device,
+		assets: &RenderAssets,
+		plan: &ResourcePlan,
+		target_size: Size<u32>
+	) {
+		self.allocate_resources(device, plan, target_size);
+		self.update_bind_groups(device, assets, plan);
+	}
+
+	fn allocate_resources(&mut self, device: &Device, plan: &ResourcePlan, target_size: Size<u32>) {
+		let mut texture_pool = mem::take(&mut self.texture_slots);
+		self.texture_slots = plan.texture_slots.iter()
+			.map(|slot| {
+				let key = TextureKey {
+					size: slot.desc.size.resolve(target_size),
+					format: slot.desc.format,
+					usage: slot.desc.usage
+				};
+				match texture_pool.iter().position(|pooled| pooled.key == key) {
+					Some(index) => texture_pool.swap_remove(index),
+					None => PooledTexture::new(device, key, &slot.label)
+				}
+			})
+			.collect();
+		self.textures = plan.textures.clone();
+
+		let mut buffer_pool = mem::take(&mut self.buffer_slots);
+		self.buffer_slots = plan.buffer_slots.iter()
+			.map(|slot| match buffer_pool.iter().position(|pooled| pooled.desc == slot.desc) {
+				Some(index) => buffer_pool.swap_remove(index),
+				None => PooledBuffer::new(device, slot.desc, &slot.label)
+			})
+			.collect();
+		self.buffers = plan.buffers.clone();
+	}
+
+	/// Recreates bind groups whose layout or resources were recreated.
+	/// Bind group that fails is kept until it can be recreated
+	pub fn update_bind_groups(&mut self, device: &Device, assets: &RenderAssets, plan: &ResourcePlan) {
+		for desc in &plan.bind_groups {
+			match self.update_bind_group(device, assets, desc, self.bind_groups.get(desc.name)) {
+				Ok(Some(bind_group)) => {
+					self.bind_groups.insert(desc.name, bind_group);
+				}
+				Ok(None) => {}
+				Err(err) => error!("{}", err)
+			}
+		}
+	}
+
+	pub fn get_texture(&self, name: &str) -> Option<&Texture> {
+		self.textures.get(name)
+			.map(|&slot| &self.texture_slots[slot].texture)
+	}
+
+	#[allow(dead_code)]
+	pub fn get_texture_view(&self, name: &str) -> Option<&TextureView> {
+		self.textures.get(name)
+			.map(|&slot| &self.texture_slots[slot].view)
+	}
+
+	#[allow(dead_code)]
+	pub fn get_buffer(&self, name: &str) -> Option<&Buffer> {
+		self.buffers.get(name)
+			.map(|&slot| &self.buffer_slots[slot].buffer)
+	}
+
+	/// # Panics
+	/// If bind group is not declared by any pass of the graph
+	pub fn bind_group(&self, name: &str) -> &BindGroup {
+		self.bind_groups.get(name)
+			.map(|cached| &cached.bind_group)
+			.unwrap_or_else(|| panic!("Missing bind group {}", name))
+	}
+}
+
+
+#[derive(PartialEq)]
+struct TextureKey {
+	size: Size<u32>,
+	format: TextureFormat,
+	usage: TextureUsages
+}
+
+struct PooledTexture {
+	key: TextureKey,
+	texture: Texture,
+	view: TextureView
+}
+
+impl PooledTexture {
+	fn new(device: &Device, key: TextureKey, label: &str) -> Self {
+		let texture = device.create_texture(&TextureDescriptor {
+			label: Some(label),
+			size: key.size.into(),
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format: key.format,
+			// Any transient texture can be captured
+			usage: key.usage | TextureUsages::COPY_SRC,
+			view_formats: &[]
+		});
+		let view = texture.create_view(&default());
+		Self { key, texture, view }
+	}
+}
+
+struct PooledBuffer {
+	desc: BufferDesc,
+	buffer: Buffer
+}
+
+impl PooledBuffer {
+	fn new(device: &Device, desc: BufferDesc, label: &str) -> Self {
+		let buffer = device.create_buffer(&BufferDescriptor {
+			label: Some(label),
+			size: desc.size,
+			usage: desc.usage,
+			mapped_at_creation: false
+		});
+		Self { desc, buffer }
+	}
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use super::{BindGroupDesc, BufferDesc, TextureDesc};
+
+
+/// Transient resources of the render graph with physical slots assigned.
+/// Resources with equal descriptors and non-overlapping lifetimes share a slot
+#[derive(Default)]
+pub(crate) struct ResourcePlan {
+	pub textures: HashMap<&'static str, usize>,
+	pub texture_slots: Vec<ResourceSlot<TextureDesc>>,
+	pub buffers: HashMap<&'static str, usize>,
+	pub buffer_slots: Vec<ResourceSlot<BufferDesc>>,
+	pub bind_groups: Vec<BindGroupDesc>
+}
+
+impl ResourcePlan {
+	/// Whether memory of the texture is reused by a resource used after it,
+	/// its contents are then overwritten by the end of the frame
+	pub fn is_texture_aliased_later(&self, name: &str) -> bool {
+		self.textures.get(name)
+			.is_some_and(|&slot| self.texture_slots[slot].last_resource != name)
+	}
+}
+
+pub(crate) struct ResourceSlot<D> {
+	pub desc: D,
+	pub label: String,
+	/// Resource that uses the slot last
+	last_resource: &'static str,
+	/// Position of the last pass using the slot
+	last_use: usize
+}
+
+
+/// Transient resource used by passes from `first_use` to `last_use` in execution order
+pub(crate) struct TransientResource<D> {
+	pub name: &'static str,
+	pub desc: D,
+	pub first_use: usize,
+	pub last_use: usize
+}
+
+pub(crate) fn assign_slots<D>(
+	mut resources: Vec<TransientResource<D>>
+) -> (HashMap<&'static str, usize>, Vec<ResourceSlot<D>>)
+where D: PartialEq {
+	resources.sort_by_key(|resource| resource.first_use);
+
+	let mut assigned = HashMap::with_capacity(resources.len());
+	let mut slots = Vec::<ResourceSlot<D>>::new();
+	for resource in resources {
+		let free_slot = slots.iter().position(|slot|
+			slot.desc == resource.desc && slot.last_use < resource.first_use
+		);
+		let index = match free_slot {
+			Some(index) => {
+				let slot = &mut slots[index];
+				slot.label.push('|');
+				slot.label.push_str(resource.name);
+				slot.last_resource = resource.name;
+				slot.last_use = resource.last_use;
+				index
+			}
+			None => {
+				slots.push(ResourceSlot {
+					desc: resource.desc,
+					label: resource.name.to_owned(),
+					last_resource: resource.name,
+					last_use: resource.last_use
+				});
+				slots.len() - 1
+			}
+		};
+		assigned.insert(resource.name, index);
+	}
+	(assigned, slots)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use starflow_util::Size;
+
+	use crate::resources::{ResourceSize, TextureDesc, TextureFormat, TextureUsages};
+
+	use super::*;
+
+
+	const USAGE: TextureUsages = TextureUsages::TEXTURE_BINDING;
+
+	fn texture(format: TextureFormat) -> TextureDesc {
+		TextureDesc::target_sized(format, USAGE)
+	}
+
+	fn resource(name: &'static str, desc: TextureDesc, uses: (usize, usize)) -> TransientResource<TextureDesc> {
+		TransientResource { name, desc, first_use: uses.0, last_use: uses.1 }
+	}
+
+	#[test]
+	fn overlapping_lifetimes_get_separate_slots() {
+		let desc = texture(TextureFormat::Rgba8Unorm);
+		let (assigned, slots) = assign_slots(vec![
+			resource("a", desc, (0, 2)),
+			resource("b", desc, (1, 3))
+		]);
+		assert_eq!(slots.len(), 2);
+		assert_ne!(assigned["a"], assigned["b"]);
+	}
+
+	#[test]
+	fn resource_is_not_aliased_in_its_last_pass() {
+		let desc = texture(TextureFormat::Rgba8Unorm);
+		// Pass 1 reads "a" and writes "b"
+		let (assigned, _) = assign_slots(vec![
+			resource("a", desc, (0, 1)),
+			resource("b", desc, (1, 2))
+		]);
+		assert_ne!(assigned["a"], assigned["b"]);
+	}
+
+	#[test]
+	fn disjoint_lifetimes_share_matching_slot() {
+		let desc = texture(TextureFormat::Rgba8Unorm);
+		let (assigned, slots) = assign_slots(vec![
+			resource("c", desc, (4, 5)),
+			resource("a", desc, (0, 1)),
+			resource("b", desc, (2, 3))
+		]);
+		assert_eq!(slots.len(), 1);
+		assert_eq!(slots[0].label, "a|b|c");
+		assert!(assigned.values().all(|&slot| slot == 0));
+	}
+
+	#[test]
+	fn slots_are_reused_only_by_equal_descriptors() {
+		let desc = texture(TextureFormat::Rgba8Unorm);
+		let other_format = texture(TextureFormat::Rgba16Float);
+		let other_size = TextureDesc { size: ResourceSize::Relative(0.5), ..desc };
+		let absolute = TextureDesc { size: ResourceSize::Absolute(Size::new(64, 64)), ..desc };
+		let (assigned, slots) = assign_slots(vec![
+			resource("a", desc, (0, 0)),
+			resource("format", other_format, (1, 1)),
+			resource("half", other_size, (2, 2)),
+			resource("absolute", absolute, (3, 3)),
+			resource("same", desc, (4, 4))
+		]);
+		assert_eq!(slots.len(), 4);
+		assert_eq!(assigned["same"], assigned["a"]);
+	}
+
+	#[test]
+	fn live_resources_never_share_slot() {
+		let formats = [TextureFormat::Rgba8Unorm, TextureFormat::R32Float];
+		// Deterministic pseudo-random lifetimes
+		let mut state = 0x2545_f491_u32;
+		let mut next = |bound: u32| {
+			state ^= state << 13;
+			state ^= state >> 17;
+			state ^= state << 5;
+			(state % bound) as usize
+		};
+		let names = (0..32).map(|index| &*format!("r{}", index).leak()).collect::<Vec<_>>();
+		for _ in 0..100 {
+			let resources = names.iter()
+				.map(|&name| {
+					let first_use = next(16);
+					let last_use = first_use + next(4);
+					resource(name, texture(formats[next(2)]), (first_use, last_use))
+				})
+				.collect::<Vec<_>>();
+			let uses = resources.iter()
+				.map(|resource| (resource.name, (resource.desc, resource.first_use, resource.last_use)))
+				.collect::<HashMap<_, _>>();
+
+			let (assigned, slots) = assign_slots(resources);
+			for (a, &slot_a) in &assigned {
+				let (desc, first_use, last_use) = uses[a];
+				assert!(slots[slot_a].desc == desc);
+				for (b, &slot_b) in &assigned {
+					let (_, other_first, other_last) = uses[b];
+					let overlaps = first_use <= other_last && other_first <= last_use;
+					assert!(a == b || slot_a != slot_b || !overlaps, "{} and {} are aliased while live", a, b);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn only_last_resource_of_slot_survives_frame() {
+		let desc = texture(TextureFormat::Rgba8Unorm);
+		let (textures, texture_slots) = assign_slots(vec![
+			resource("a", desc, (0, 1)),
+			resource("b", desc, (2, 3)),
+			resource("c", texture(TextureFormat::R32Float), (0, 3))
+		]);
+		let plan = ResourcePlan { textures, texture_slots, ..Default::default() };
+		assert!(plan.is_texture_aliased_later("a"));
+		assert!(!plan.is_texture_aliased_later("b"));
+		assert!(!plan.is_texture_aliased_later("c"));
+		assert!(!plan.is_texture_aliased_later("missing"));
+	}
+}
@@ -0,0 +1,341 @@
+mod common;
+
+use std::fs;
+
+use starflow_util::Size;
+use starflow_render::assets::{
+	desc::{self, BindGroupLayout, ColorTarget, FragmentStage, ShaderModule, VertexStage},
+	util::binding, AssetError, AssetErrors, AssetId, AssetKind, AssetManifest, CreationMode,
+	LayoutMismatchKind, ShaderReflection
+};
+use wgpu::{
+	BindGroupLayoutEntry, BindingType, BlendState, BufferBindingType, ColorWrites, ComputePipeline,
+	PipelineLayout, RenderPipeline, ShaderSource, ShaderStages, StorageTextureAccess,
+	TextureFormat, TextureViewDimension
+};
+
+use common::{headless_renderer, headless_renderer_with};
+
+
+const SIZE: Size<u32> = Size { width: 16, height: 16 };
+
+/// Every entry fails, in creation order
+const BROKEN_MANIFEST: &str = r#"(
+	shader_modules: {
+		"broken_include": (source: File("fullscreen.wgsl"), defines: {"location": "+"}),
+		"broken_wgsl": (source: Wgsl("@compute @workgroup_size(1)\nfn main( {}")),
+	},
+	pipeline_layouts: {
+		"wrong_kind": Explicit(bind_group_layouts: ["fullscreen"]),
+	},
+	compute_pipelines: {
+		"missing_module": (module: "missing"),
+	},
+)"#;
+
+fn create_broken_assets(mode: CreationMode) -> Option<AssetErrors> {
+	let mut renderer = headless_renderer(SIZE)?;
+	let manifest = AssetManifest::parse(BROKEN_MANIFEST).unwrap();
+	Some(renderer.create_assets(|ctx| manifest.create(ctx, mode)).unwrap_err())
+}
+
+#[test]
+fn pipeline_dependencies() {
+	let Some(renderer) = headless_renderer(SIZE) else { return };
+	let dependencies = renderer.asset_dependencies();
+
+	let blit = AssetId::new(AssetKind::RenderPipeline, "blit");
+	assert_eq!(dependencies.dependencies(&blit), [
+		AssetId::new(AssetKind::PipelineLayout, "blit"),
+		AssetId::new(AssetKind::ShaderModule, "fullscreen"),
+		AssetId::new(AssetKind::ShaderModule, "blit")
+	]);
+}
+
+#[test]
+fn dependents_are_ordered() {
+	let Some(renderer) = headless_renderer(SIZE) else { return };
+	let dependencies = renderer.asset_dependencies();
+
+	let layout = AssetId::new(AssetKind::BindGroupLayout, "output_texture");
+	assert_eq!(dependencies.dependents(&layout), [
+		AssetId::new(AssetKind::PipelineLayout, "main_pass"),
+		AssetId::new(AssetKind::ComputePipeline, "main_pass")
+	]);
+}
+
+#[test]
+fn replacement_rebuilds_dependents() {
+	let Some(mut renderer) = headless_renderer(SIZE) else { return };
+	renderer.wait_for_pipelines();
+	let layout = renderer.asset::<PipelineLayout>("main_pass").unwrap().clone();
+	let pipeline = renderer.asset::<ComputePipeline>("main_pass").unwrap().clone();
+
+	let entries = [binding(0)
+		.visibility(ShaderStages::COMPUTE)
+		.texture_storage_2d(TextureFormat::Rgba8Unorm, StorageTextureAccess::WriteOnly)];
+	renderer.create_assets(|ctx| {
+		ctx.create(BindGroupLayout::new("output_texture", &entries)).unwrap();
+	});
+	renderer.wait_for_pipelines();
+
+	assert_ne!(renderer.asset::<PipelineLayout>("main_pass").unwrap(), &layout);
+	assert_ne!(renderer.asset::<ComputePipeline>("main_pass").unwrap(), &pipeline);
+	assert_eq!(renderer.pending_pipelines(), 0);
+	renderer.capture_frame().unwrap();
+}
+
+#[test]
+fn manifest_loaded_from_file() {
+	let manifest = include_str!("../../../assets/render_assets.ron").replace(
+		"shader_modules: {",
+		"shader_modules: {\n\t\t\"extra\": (source: Wgsl(\"@compute @workgroup_size(1) fn main() {}\")),"
+	);
+	let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("render_assets.ron");
+	fs::write(&path, manifest).unwrap();
+
+	let Some(mut renderer) = headless_renderer_with(SIZE, |config| config.asset_manifest(&path))
+	else { return };
+	assert_eq!(renderer.asset_dependencies().kind_of("extra"), Some(AssetKind::ShaderModule));
+	renderer.capture_frame().unwrap();
+}
+
+#[test]
+fn continue_on_error_collects_failures() {
+	let Some(errors) = create_broken_assets(CreationMode::ContinueOnError) else { return };
+	let assets = errors.0.iter()
+		.map(|diagnostic| diagnostic.asset.clone())
+		.collect::<Vec<_>>();
+	assert_eq!(assets, [
+		AssetId::new(AssetKind::ShaderModule, "broken_include"),
+		AssetId::new(AssetKind::ShaderModule, "broken_wgsl"),
+		AssetId::new(AssetKind::PipelineLayout, "wrong_kind"),
+		AssetId::new(AssetKind::ComputePipeline, "missing_module")
+	]);
+	assert!(matches!(
+		&errors.0[2].error,
+		AssetError::KindMismatch { key, expected: AssetKind::BindGroupLayout, found: AssetKind::ShaderModule }
+			if key == "fullscreen"
+	));
+	assert!(matches!(&errors.0[3].error, AssetError::MissingDependency(key) if key == "missing"));
+}
+
+#[test]
+fn fail_fast_stops_at_first_failure() {
+	let Some(errors) = create_broken_assets(CreationMode::FailFast) else { return };
+	assert_eq!(errors.0.len(), 1);
+	assert_eq!(errors.0[0].asset, AssetId::new(AssetKind::ShaderModule, "broken_include"));
+}
+
+#[test]
+fn shader_errors_point_at_original_lines() {
+	let Some(errors) = create_broken_assets(CreationMode::ContinueOnError) else { return };
+	let span = |index: usize| {
+		let AssetError::ShaderCompilation(err) = &errors.0[index].error else {
+			panic!("Unexpected error: {}", errors.0[index].error);
+		};
+		let span = err.messages[0].location.as_ref().unwrap();
+		(span.file.to_string(), span.line)
+	};
+	// Define is substituted in the included file
+	assert_eq!(span(0), ("fullscreen_output.wgsl".to_owned(), 4));
+	assert_eq!(span(1), ("broken_wgsl".to_owned(), 2));
+}
+
+#[test]
+fn duplicate_key_is_rejected() {
+	let Some(mut renderer) = headless_renderer(SIZE) else { return };
+	let source = "@compute @workgroup_size(1) fn main() {}";
+	let duplicate = renderer.create_assets(|ctx| {
+		ctx.create(ShaderModule::new("duplicate", ShaderSource::Wgsl(source.into()))).unwrap();
+		ctx.create(ShaderModule::new("duplicate", ShaderSource::Wgsl(source.into())))
+			.map(drop)
+			.map_err(AssetError::into_owned)
+	});
+	assert!(matches!(
+		duplicate,
+		Err(AssetError::DuplicateKey(id)) if id == AssetId::new(AssetKind::ShaderModule, "duplicate")
+	));
+}
+
+#[test]
+fn reflected_bindings() {
+	let reflection = ShaderReflection::from_wgsl("
+		@group(0) @binding(0) var output: texture_storage_2d<rgba8unorm, write>;
+		@group(1) @binding(2) var<uniform> scale: f32;
+		@group(1) @binding(3) var<storage, read> unused: array<u32>;
+
+		override HEIGHT: u32 = 4;
+
+		@compute @workgroup_size(8, HEIGHT)
+		fn main(@builtin(global_invocation_id) id: vec3u) {
+			textureStore(output, id.xy, vec4f(scale));
+		}
+	").unwrap();
+
+	assert_eq!(reflection.group_count(), 2);
+	let entry_point = reflection.entry_point(ShaderStages::COMPUTE, None).unwrap();
+	assert_eq!(entry_point.workgroup_size, [8, 4, 1]);
+	assert_eq!(entry_point.specialized_workgroup_size(&[("HEIGHT", 2.0)]), [8, 2, 1]);
+
+	let output = &reflection.bindings[&(0, 0)];
+	assert_eq!(output.visibility, ShaderStages::COMPUTE);
+	assert_eq!(output.ty.as_ref().unwrap(), &BindingType::StorageTexture {
+		access: StorageTextureAccess::WriteOnly,
+		format: TextureFormat::Rgba8Unorm,
+		view_dimension: TextureViewDimension::D2
+	});
+	assert!(matches!(
+		reflection.bindings[&(1, 2)].ty,
+		Ok(BindingType::Buffer { ty: BufferBindingType::Uniform, .. })
+	));
+	// Bindings not used by any entry point are not part of the interface
+	assert!(!reflection.bindings.contains_key(&(1, 3)));
+}
+
+/// Uses uniform buffer at `@group(0) @binding(0)` and storage buffer at `@group(1) @binding(0)`
+const BINDINGS_SHADER: &str = "
+	@group(0) @binding(0) var<uniform> scale: f32;
+	@group(1) @binding(0) var<storage, read_write> values: array<f32>;
+
+	@compute @workgroup_size(1)
+	fn main() {
+		values[0] *= scale;
+	}
+";
+
+/// Creates compute pipeline using [`BINDINGS_SHADER`] with a bind group layout per group
+fn create_bindings_pipeline(
+	groups: &[&[BindGroupLayoutEntry]]
+) -> Option<Result<(), AssetError<'static>>> {
+	let mut renderer = headless_renderer(SIZE)?;
+	let keys = (0..groups.len())
+		.map(|group| format!("bindings@{}", group))
+		.collect::<Vec<_>>();
+	let bind_group_layouts = keys.iter()
+		.map(String::as_str)
+		.collect::<Vec<_>>();
+	Some(renderer.create_assets(|ctx| {
+		let source = ShaderSource::Wgsl(BINDINGS_SHADER.into());
+		ctx.create(ShaderModule::new("bindings", source)).unwrap();
+		for (key, entries) in keys.iter().zip(groups) {
+			ctx.create(BindGroupLayout::new(key, entries)).unwrap();
+		}
+		ctx.create(desc::PipelineLayout {
+			key: "bindings",
+			bind_group_layouts: &bind_group_layouts,
+			push_constant_ranges: &[]
+		}).unwrap();
+		ctx.create(desc::ComputePipeline::new("bindings", "bindings").with_layout("bindings"))
+			.map(drop)
+			.map_err(AssetError::into_owned)
+	}))
+}
+
+fn layout_mismatch(groups: &[&[BindGroupLayoutEntry]]) -> Option<LayoutMismatchKind> {
+	match create_bindings_pipeline(groups)? {
+		Err(AssetError::LayoutMismatch(mismatch)) => Some(mismatch.kind),
+		Err(err) => panic!("Unexpected error: {}", err),
+		Ok(()) => panic!("Mismatched layout was accepted")
+	}
+}
+
+fn uniform() -> BindGroupLayoutEntry {
+	binding(0).visibility(ShaderStages::COMPUTE).uniform_buffer()
+}
+
+fn storage() -> BindGroupLayoutEntry {
+	binding(0).visibility(ShaderStages::COMPUTE).storage_buffer()
+}
+
+#[test]
+fn matching_layout_is_accepted() {
+	let Some(result) = create_bindings_pipeline(&[&[uniform()], &[storage()]]) else { return };
+	result.unwrap();
+}
+
+#[test]
+fn wrong_binding_type_is_mismatch() {
+	let read_only = binding(0).visibility(ShaderStages::COMPUTE).storage_buffer_read_only();
+	let Some(kind) = layout_mismatch(&[&[uniform()], &[read_only]]) else { return };
+	assert!(matches!(kind, LayoutMismatchKind::Type {
+		layout: BindingType::Buffer { ty: BufferBindingType::Storage { read_only: true }, .. },
+		shader: BindingType::Buffer { ty: BufferBindingType::Storage { read_only: false }, .. }
+	}));
+}
+
+#[test]
+fn wrong_visibility_is_mismatch() {
+	let fragment = binding(0).visibility(ShaderStages::FRAGMENT).uniform_buffer();
+	let Some(kind) = layout_mismatch(&[&[fragment], &[storage()]]) else { return };
+	assert!(matches!(kind, LayoutMismatchKind::Visibility {
+		layout: ShaderStages::FRAGMENT,
+		shader: ShaderStages::COMPUTE
+	}));
+}
+
+#[test]
+fn missing_binding_is_mismatch() {
+	let other_binding = binding(1).visibility(ShaderStages::COMPUTE).uniform_buffer();
+	let Some(kind) = layout_mismatch(&[&[other_binding], &[storage()]]) else { return };
+	assert!(matches!(kind, LayoutMismatchKind::MissingEntry));
+}
+
+#[test]
+fn binding_outside_layout_groups_is_mismatch() {
+	let Some(kind) = layout_mismatch(&[&[uniform()]]) else { return };
+	assert!(matches!(kind, LayoutMismatchKind::MissingGroup));
+}
+
+/// Writes a color and a normal
+const TWO_TARGETS_SHADER: &str = "
+	struct Targets {
+		@location(0) color: vec4f,
+		@location(1) normal: vec4f
+	}
+
+	@vertex
+	fn vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+		return vec4f(f32(index), 0.0, 0.0, 1.0);
+	}
+
+	@fragment
+	fn fragment() -> Targets {
+		return Targets(vec4f(1.0, 0.0, 0.0, 0.5), vec4f(0.0, 0.0, 1.0, 0.0));
+	}
+";
+
+fn create_two_targets_pipeline(normal: ColorTarget) -> Option<Result<(), AssetError<'static>>> {
+	let mut renderer = headless_renderer(SIZE)?;
+	let color = ColorTarget::FINAL_TARGET.with_blend(BlendState::ALPHA_BLENDING);
+	let targets = [Some(color), Some(normal)];
+	let result = renderer.create_assets(|ctx| {
+		let source = ShaderSource::Wgsl(TWO_TARGETS_SHADER.into());
+		ctx.create(ShaderModule::new("two_targets", source)).unwrap();
+		ctx.create(desc::RenderPipeline::new("two_targets", VertexStage::new("two_targets"))
+			.with_fragment(FragmentStage::new("two_targets").with_targets(&targets)))
+			.map(drop)
+			.map_err(AssetError::into_owned)
+	});
+	if result.is_ok() {
+		assert!(renderer.asset::<RenderPipeline>("two_targets").is_some());
+	}
+	Some(result)
+}
+
+#[test]
+fn render_pipeline_with_blended_targets() {
+	let normal = ColorTarget::format(TextureFormat::Rgba16Float)
+		.with_blend(BlendState::REPLACE)
+		.with_write_mask(ColorWrites::COLOR);
+	let Some(result) = create_two_targets_pipeline(normal) else { return };
+	result.unwrap();
+}
+
+#[test]
+fn blending_unblendable_target_fails() {
+	// Blending 32-bit float targets needs a feature that isn't requested
+	let normal = ColorTarget::format(TextureFormat::Rgba32Float).with_blend(BlendState::REPLACE);
+	let Some(result) = create_two_targets_pipeline(normal) else { return };
+	assert!(matches!(result, Err(AssetError::Validation(_))));
+}
@@ -0,0 +1,109 @@
+//! Golden-image harness: renders frames headlessly and compares them against
+//! reference images in `tests/references`.
+//!
+//! Set `STARFLOW_UPDATE_GOLDEN=1` to (re)write references from the current output.
+//! On mismatch actual and diff images are written into `CARGO_TARGET_TMPDIR/golden`.
+//!
+//! Tests needing an adapter are skipped with a note on stderr if there is none.
+//! Set `STARFLOW_REQUIRE_GPU=1` to fail them instead, e.g. on CI with a software adapter.
+#![allow(dead_code)]
+
+use std::{env, fs, io::{self, Write}, path::PathBuf, thread};
+
+use futures_lite::future;
+
+use starflow_render::{
+	Backends, CapturedFrame, FrameDiff, GpuContextConfig, InitError, Renderer,
+	TextureFormat
+};
+use starflow_util::Size;
+
+
+pub const UPDATE_ENV: &str = "STARFLOW_UPDATE_GOLDEN";
+pub const REQUIRE_GPU_ENV: &str = "STARFLOW_REQUIRE_GPU";
+
+const BACKENDS: Backends = Backends::VULKAN.union(Backends::GL);
+
+/// Returns none if there is no suitable adapter, software adapters are accepted.
+/// Panics instead if [`REQUIRE_GPU_ENV`] is set
+pub fn headless_renderer(size: Size<u32>) -> Option<Renderer<'static>> {
+	headless_renderer_with(size, |config| config)
+}
+
+/// Test config is adjusted by `configure`
+pub fn headless_renderer_with(
+	size: Size<u32>,
+	configure: impl FnOnce(GpuContextConfig<'static>) -> GpuContextConfig<'static>
+) -> Option<Renderer<'static>> {
+	// No features are required so that golden images cover the baseline path
+	let config = configure(GpuContextConfig::default().backends(BACKENDS));
+	let renderer = future::block_on(
+		Renderer::new_headless(config, size, TextureFormat::Rgba8Unorm)
+	);
+	match renderer {
+		Ok(renderer) => Some(renderer),
+		Err(err @ InitError::NoAdapter { .. }) => {
+			if env::var_os(REQUIRE_GPU_ENV).is_some() {
+				panic!("No adapter while {} is set: {}", REQUIRE_GPU_ENV, err);
+			}
+			// Written past the test harness capture, so the skip is visible in passing runs
+			let _ = writeln!(
+				io::stderr(),
+				"SKIPPED {}: {}",
+				thread::current().name().unwrap_or("test"), err
+			);
+			None
+		}
+		Err(err) => panic!("Failed to create renderer: {}", err)
+	}
+}
+
+/// Panics if `frame` differs from reference image `name`
+/// by more than `tolerance` in any channel
+pub fn assert_golden(frame: &CapturedFrame, name: &str, tolerance: u8) {
+	let reference = reference_path(name);
+	if env::var_os(UPDATE_ENV).is_some() {
+		if let Some(dir) = reference.parent() {
+			fs::create_dir_all(dir)
+				.expect("Failed to create references directory");
+		}
+		frame.save_png(&reference)
+			.expect("Failed to write reference image");
+		return;
+	}
+	let expected = CapturedFrame::load_png(&reference)
+		.unwrap_or_else(|err| panic!(
+			"Failed to load reference {}: {}. Run with {}=1 to create it",
+			reference.display(), err, UPDATE_ENV
+		));
+
+	let diff = frame.compare(&expected, tolerance);
+	if diff.is_match() {
+		return;
+	}
+	let output = output_dir();
+	let actual_path = output.join(format!("{name}.actual.png"));
+	frame.save_png(&actual_path)
+		.expect("Failed to write actual image");
+	if let FrameDiff::Compared { image, .. } = &diff {
+		image.save_png(output.join(format!("{name}.diff.png")))
+			.expect("Failed to write diff image");
+	}
+	panic!(
+		"Golden image {} mismatch: {}. Output written to {}",
+		name, diff, output.display()
+	);
+}
+
+fn reference_path(name: &str) -> PathBuf {
+	PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+		.join("tests/references")
+		.join(format!("{name}.png"))
+}
+
+fn output_dir() -> PathBuf {
+	let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+	fs::create_dir_all(&dir)
+		.expect("Failed to create golden output directory");
+	dir
+}
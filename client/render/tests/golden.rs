@@ -0,0 +1,48 @@
+mod common;
+
+use starflow_render::PipelineCompilation;
+use starflow_util::Size;
+
+use common::{assert_golden, headless_renderer, headless_renderer_with};
+
+
+const SIZE: Size<u32> = Size { width: 64, height: 48 };
+const TOLERANCE: u8 = 2;
+
+#[test]
+fn final_frame() {
+	let Some(mut renderer) = headless_renderer(SIZE) else { return };
+	let frame = renderer.capture_frame()
+		.expect("Failed to capture frame");
+	assert_golden(&frame, "final_frame", TOLERANCE);
+}
+
+#[test]
+fn main_pass_output() {
+	let Some(mut renderer) = headless_renderer(SIZE) else { return };
+	let frame = renderer.capture_texture("output_texture")
+		.expect("Failed to capture output texture");
+	assert_golden(&frame, "main_pass_output", TOLERANCE);
+}
+
+#[test]
+fn resized_frame() {
+	let Some(mut renderer) = headless_renderer(SIZE) else { return };
+	renderer.resize(Size::new(0, 0));
+	renderer.resize(Size::new(40, 40));
+	let frame = renderer.capture_frame()
+		.expect("Failed to capture frame");
+	assert_golden(&frame, "resized_frame", TOLERANCE);
+}
+
+#[test]
+fn async_compiled_frame() {
+	let Some(mut renderer) = headless_renderer_with(SIZE, |config| {
+		config.pipeline_compilation(PipelineCompilation::parallel())
+	}) else { return };
+	renderer.wait_for_pipelines();
+	assert_eq!(renderer.pending_pipelines(), 0);
+	let frame = renderer.capture_frame()
+		.expect("Failed to capture frame");
+	assert_golden(&frame, "final_frame", TOLERANCE);
+}
@@ -0,0 +1,58 @@
+use std::num::NonZero;
+
+use starflow_render::assets::util::{
+	binding, SamplerBindingType, ShaderStages, StorageTextureAccess, TextureFormat,
+	TextureSampleType
+};
+use wgpu::{BindingType, BufferBindingType, TextureViewDimension};
+
+
+#[test]
+fn buffer_entries() {
+	let size = NonZero::new(64).unwrap();
+	let entry = binding(1)
+		.visibility(ShaderStages::VERTEX_FRAGMENT)
+		.dynamic_offset()
+		.min_binding_size(size)
+		.storage_buffer_read_only();
+
+	assert_eq!(entry.binding, 1);
+	assert_eq!(entry.visibility, ShaderStages::VERTEX_FRAGMENT);
+	assert_eq!(entry.ty, BindingType::Buffer {
+		ty: BufferBindingType::Storage { read_only: true },
+		has_dynamic_offset: true,
+		min_binding_size: Some(size)
+	});
+}
+
+#[test]
+fn texture_entries() {
+	let count = NonZero::new(4).unwrap();
+	let array = binding(0)
+		.count(count)
+		.texture_cube_array(TextureSampleType::Float { filterable: true });
+	assert_eq!(array.count, Some(count));
+	assert_eq!(array.ty, BindingType::Texture {
+		sample_type: TextureSampleType::Float { filterable: true },
+		view_dimension: TextureViewDimension::CubeArray,
+		multisampled: false
+	});
+
+	assert_eq!(binding(0).texture_depth_multisampled_2d().ty, BindingType::Texture {
+		sample_type: TextureSampleType::Depth,
+		view_dimension: TextureViewDimension::D2,
+		multisampled: true
+	});
+	assert_eq!(
+		binding(0).texture_storage_3d(TextureFormat::R32Float, StorageTextureAccess::ReadWrite).ty,
+		BindingType::StorageTexture {
+			access: StorageTextureAccess::ReadWrite,
+			format: TextureFormat::R32Float,
+			view_dimension: TextureViewDimension::D3
+		}
+	);
+	assert_eq!(
+		binding(0).sampler_comparison().ty,
+		BindingType::Sampler(SamplerBindingType::Comparison)
+	);
+}
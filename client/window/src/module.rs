@@ -3,17 +3,18 @@ use std::sync::Arc;
 use winit::window::Window;
 
 use glued::module_impl;
-use starflow_util::Size;
+use starflow_util::{Mailbox, Size};
 
 
 pub struct WindowModule {
-	pub window: Arc<Window>
+	pub window: Arc<Window>,
+	resized: Mailbox<Size<u32>>
 }
 
 impl WindowModule {
 	pub fn new(window: Window) -> Self {
 		let window = Arc::new(window);
-		Self { window }
+		Self { window, resized: Mailbox::default() }
 	}
 
 	pub fn with_title(self, title: &str) -> Self {
@@ -30,6 +31,11 @@ impl WindowModule {
 			.inner_size()
 			.into()
 	}
+
+	/// Receives the latest window size after resize
+	pub fn resize_events(&self) -> Mailbox<Size<u32>> {
+		self.resized.clone()
+	}
 }
 
 #[module_impl(A)]
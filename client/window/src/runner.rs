@@ -8,6 +8,8 @@ use winit::{
 };
 
 use glued::{AppRunner, ModularApp};
+use starflow_util::{Mailbox, Size};
+
 use crate::WindowModule;
 
 
@@ -33,13 +35,14 @@ impl AppRunner for WinitRunner {
 
 pub(super) struct AppHandler<A>
 where A: ModularApp {
-	app: Option<A>
+	app: Option<A>,
+	resized: Mailbox<Size<u32>>
 }
 
 impl<A> Default for AppHandler<A>
 where A: ModularApp {
 	fn default() -> Self {
-		Self { app: Default::default() }
+		Self { app: Default::default(), resized: Default::default() }
 	}
 }
 
@@ -49,7 +52,9 @@ where A: ModularApp + From<WindowModule> {
 		if self.app.is_none() {
 			let window = event_loop.create_window(default())
 				.expect("Failed to create window");
-			self.app = Some(A::from(WindowModule::new(window)));
+			let window = WindowModule::new(window);
+			self.resized = window.resize_events();
+			self.app = Some(A::from(window));
 			self.app_mut().setup();
 		}
 	}
@@ -62,6 +67,7 @@ where A: ModularApp + From<WindowModule> {
 	) {
 		match event {
 			WindowEvent::CloseRequested => event_loop.exit(),
+			WindowEvent::Resized(size) => self.resized.send(size.into()),
 			_ => {}
 		}
 	}
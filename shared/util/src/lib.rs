@@ -1,5 +1,7 @@
+pub use mailbox::*;
 pub use registry::*;
 pub use size::*;
 
+mod mailbox;
 mod registry;
 mod size;
@@ -0,0 +1,33 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+
+/// Shared single-value slot, sending overwrites value that was not received yet.
+/// Clones refer to the same slot
+pub struct Mailbox<T>(Arc<Mutex<Option<T>>>);
+
+impl<T> Default for Mailbox<T> {
+	fn default() -> Self {
+		Self(Arc::new(Mutex::new(None)))
+	}
+}
+
+impl<T> Clone for Mailbox<T> {
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+
+impl<T> Mailbox<T> {
+	pub fn send(&self, value: T) {
+		*self.lock() = Some(value);
+	}
+
+	/// Takes the latest sent value if any
+	pub fn receive(&self) -> Option<T> {
+		self.lock().take()
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Option<T>> {
+		self.0.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
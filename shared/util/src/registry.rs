@@ -1,19 +1,35 @@
-use std::{borrow::Borrow, hash::Hash, marker::PhantomData, ops::Index};
+pub use concurrent::*;
+
+mod concurrent;
+
+
+use std::{borrow::Borrow, fmt, hash::Hash, marker::PhantomData, ops::Index};
 use default::default;
 
 use ahash::AHashMap;
 
 
+/// Values by key with stable handles.
+/// Slots of removed values are reused, handles to them become stale
 pub struct Registry<K, V> {
 	key_to_handle: AHashMap<K, Handle<V>>,
-	values: Vec<V>
+	slots: Vec<Slot<K, V>>,
+	/// Indices of vacant slots
+	free: Vec<usize>
+}
+
+struct Slot<K, V> {
+	/// Incremented when value is removed
+	generation: u32,
+	entry: Option<(K, V)>
 }
 
 impl<K, V> Default for Registry<K, V> {
 	fn default() -> Self {
 		Self {
 			key_to_handle: default(),
-			values: default()
+			slots: default(),
+			free: default()
 		}
 	}
 }
@@ -22,63 +38,224 @@ impl<K, V> Default for Registry<K, V> {
 impl<K, V> Registry<K, V>
 where K: Eq + Hash  {
 
-	pub fn set(&mut self, key: K, value: V) -> Handle<V> {
-		if let Some(handle) = self.key_to_handle.get(&key) {
-			self.values[handle.0] = value;
-			handle.clone()
+	/// Replaces value of existing key in place, its handle stays valid
+	pub fn set(&mut self, key: K, value: V) -> Handle<V>
+	where K: Clone {
+		if let Some(&handle) = self.key_to_handle.get(&key) {
+			self.slots[handle.index].entry = Some((key, value));
+			return handle;
 		}
-		else {
-			let handle = Handle::new(self.values.len());
-			self.key_to_handle.insert(key, handle.clone());
-			self.values.push(value);
-			handle
+		self.insert(key, value)
+	}
+
+	/// Inserts value created by `create` unless key is present
+	pub fn get_or_insert_with(&mut self, key: K, create: impl FnOnce() -> V) -> Handle<V>
+	where K: Clone {
+		match self.key_to_handle.get(&key) {
+			Some(&handle) => handle,
+			None => self.insert(key, create())
 		}
 	}
 
-	pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+	fn insert(&mut self, key: K, value: V) -> Handle<V>
+	where K: Clone {
+		let entry = Some((key.clone(), value));
+		let index = match self.free.pop() {
+			Some(index) => {
+				self.slots[index].entry = entry;
+				index
+			}
+			None => {
+				self.slots.push(Slot { generation: 0, entry });
+				self.slots.len() - 1
+			}
+		};
+		let handle = Handle::new(index, self.slots[index].generation);
+		self.key_to_handle.insert(key, handle);
+		handle
+	}
+
+	/// Handles to removed value become stale
+	pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq + ?Sized
+	{
+		let handle = self.key_to_handle.remove(key)?;
+		let slot = &mut self.slots[handle.index];
+		slot.generation = slot.generation.wrapping_add(1);
+		self.free.push(handle.index);
+		slot.entry.take().map(|(_, value)| value)
+	}
+
+	pub fn get<Q>(&self, key: &Q) -> Option<&V>
 	where
 		K: Borrow<Q>,
-		Q: Hash + Eq
+		Q: Hash + Eq + ?Sized
 	{
 		self.get_handle(key).map(|id| &self[id])
 	}
 
-	pub fn get_handle<Q: ?Sized>(&self, key: &Q) -> Option<Handle<V>>
+	pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
 	where
 		K: Borrow<Q>,
-		Q: Hash + Eq
+		Q: Hash + Eq + ?Sized
 	{
-		self.key_to_handle.get(key).cloned()
+		let handle = self.get_handle(key)?;
+		self.slots[handle.index].entry.as_mut().map(|(_, value)| value)
+	}
+
+	pub fn contains_key<Q>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq + ?Sized
+	{
+		self.key_to_handle.contains_key(key)
+	}
+
+	pub fn get_handle<Q>(&self, key: &Q) -> Option<Handle<V>>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq + ?Sized
+	{
+		self.key_to_handle.get(key).copied()
+	}
+}
+
+impl<K, V> Registry<K, V> {
+	/// Returns `None` if handle is stale
+	pub fn get_by_handle(&self, handle: Handle<V>) -> Option<&V> {
+		self.entry(handle).map(|(_, value)| value)
+	}
+
+	/// Returns `None` if handle is stale
+	pub fn key_of(&self, handle: Handle<V>) -> Option<&K> {
+		self.entry(handle).map(|(key, _)| key)
+	}
+
+	pub fn contains_handle(&self, handle: Handle<V>) -> bool {
+		self.entry(handle).is_some()
+	}
+
+	pub fn len(&self) -> usize {
+		self.slots.len() - self.free.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Live values in slot order
+	pub fn iter(&self) -> impl Iterator<Item = (Handle<V>, &V)> {
+		self.slots.iter()
+			.enumerate()
+			.filter_map(|(index, slot)| slot.entry
+				.as_ref()
+				.map(|(_, value)| (Handle::new(index, slot.generation), value)))
+	}
+
+	/// Live values in slot order
+	pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<V>, &mut V)> {
+		self.slots.iter_mut()
+			.enumerate()
+			.filter_map(|(index, slot)| slot.entry
+				.as_mut()
+				.map(|(_, value)| (Handle::new(index, slot.generation), value)))
+	}
+
+	/// Keys of live values in slot order
+	pub fn keys(&self) -> impl Iterator<Item = &K> {
+		self.slots.iter()
+			.filter_map(|slot| slot.entry.as_ref().map(|(key, _)| key))
+	}
+
+	fn entry(&self, handle: Handle<V>) -> Option<&(K, V)> {
+		self.slots.get(handle.index)
+			.filter(|slot| slot.generation == handle.generation)
+			.and_then(|slot| slot.entry.as_ref())
+	}
+}
+
+/// Replaces values of existing keys
+impl<K, V> Extend<(K, V)> for Registry<K, V>
+where K: Eq + Hash + Clone {
+	fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+		for (key, value) in iter {
+			self.set(key, value);
+		}
 	}
 }
 
+impl<K, V> FromIterator<(K, V)> for Registry<K, V>
+where K: Eq + Hash + Clone {
+	fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+		let mut registry = Self::default();
+		registry.extend(iter);
+		registry
+	}
+}
+
+/// Formats live entries in slot order
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Registry<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_map()
+			.entries(self.slots.iter()
+				.filter_map(|slot| slot.entry.as_ref())
+				.map(|(key, value)| (key, value)))
+			.finish()
+	}
+}
+
+/// Panics if handle is stale
 impl<K, V> Index<Handle<V>> for Registry<K, V> {
 	type Output = V;
 
 	fn index(&self, index: Handle<V>) -> &Self::Output {
-		&self.values[index.0]
+		self.get_by_handle(index)
+			.expect("Stale registry handle")
 	}
 }
 
+/// Panics if handle is stale
 impl<K, V> Index<&Handle<V>> for Registry<K, V> {
 	type Output = V;
 
 	fn index(&self, index: &Handle<V>) -> &Self::Output {
-		&self.values[index.0]
+		&self[*index]
 	}
 }
 
-#[derive(Copy)]
-pub struct Handle<V>(usize, PhantomData<V>);
+/// Slot index and generation of the value it was created for
+pub struct Handle<V> {
+	index: usize,
+	generation: u32,
+	value: PhantomData<fn() -> V>
+}
 
 impl<V> Clone for Handle<V> {
 	fn clone(&self) -> Self {
-		Self(self.0, self.1)
+		*self
+	}
+}
+
+impl<V> Copy for Handle<V> {}
+
+impl<V> PartialEq for Handle<V> {
+	fn eq(&self, other: &Self) -> bool {
+		self.index == other.index && self.generation == other.generation
+	}
+}
+
+impl<V> Eq for Handle<V> {}
+
+impl<V> fmt::Debug for Handle<V> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Handle({}v{})", self.index, self.generation)
 	}
 }
 
 impl<V> Handle<V> {
-	fn new(id: usize) -> Self {
-		Self(id, PhantomData)
+	fn new(index: usize, generation: u32) -> Self {
+		Self { index, generation, value: PhantomData }
 	}
 }
@@ -0,0 +1,204 @@
+use core::num::NonZero;
+use std::{borrow::Borrow, hash::{BuildHasher, Hash}, sync::PoisonError};
+#[cfg(not(loom))]
+use std::sync::{RwLock, RwLockReadGuard};
+
+use ahash::RandomState;
+#[cfg(loom)]
+use loom::sync::{RwLock, RwLockReadGuard};
+
+use super::{Handle, Registry, Slot};
+
+
+const DEFAULT_SHARDS: NonZero<usize> = NonZero::new(16).unwrap();
+
+/// [`Registry`] shared between threads.
+/// Keys are distributed between independently locked shards,
+/// so only accesses to the same shard contend.
+/// Handles index slots of all shards and stay valid in [`snapshot`](Self::snapshot).
+/// `S` selects shard of a key
+pub struct ConcurrentRegistry<K, V, S = RandomState> {
+	shards: Box<[RwLock<Registry<K, V>>]>,
+	hasher: S
+}
+
+impl<K, V> Default for ConcurrentRegistry<K, V> {
+	fn default() -> Self {
+		Self::new(DEFAULT_SHARDS)
+	}
+}
+
+impl<K, V> ConcurrentRegistry<K, V> {
+	pub fn new(shards: NonZero<usize>) -> Self {
+		Self::with_hasher(shards, RandomState::new())
+	}
+}
+
+impl<K, V, S> ConcurrentRegistry<K, V, S> {
+	pub fn with_hasher(shards: NonZero<usize>, hasher: S) -> Self {
+		Self {
+			shards: (0..shards.get()).map(|_| RwLock::default()).collect(),
+			hasher
+		}
+	}
+
+	/// Returns `None` if handle is stale
+	pub fn get_by_handle(&self, handle: Handle<V>) -> Option<V>
+	where V: Clone {
+		self.read_by_handle(handle, V::clone)
+	}
+
+	/// Calls `read` with value while its shard is locked.
+	/// Returns `None` if handle is stale
+	pub fn read_by_handle<R>(&self, handle: Handle<V>, read: impl FnOnce(&V) -> R) -> Option<R> {
+		let (shard, local) = self.local_handle(handle);
+		self.read_shard(shard).get_by_handle(local).map(read)
+	}
+
+	pub fn contains_handle(&self, handle: Handle<V>) -> bool {
+		self.read_by_handle(handle, |_| ()).is_some()
+	}
+
+	/// Number of values at the time of the call
+	pub fn len(&self) -> usize {
+		(0..self.shards.len()).map(|shard| self.read_shard(shard).len()).sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Index of shard and handle to its registry
+	fn local_handle(&self, handle: Handle<V>) -> (usize, Handle<V>) {
+		let count = self.shards.len();
+		(handle.index % count, Handle::new(handle.index / count, handle.generation))
+	}
+
+	fn global_handle(&self, shard: usize, local: Handle<V>) -> Handle<V> {
+		Handle::new(local.index * self.shards.len() + shard, local.generation)
+	}
+
+	fn read_shard(&self, shard: usize) -> RwLockReadGuard<'_, Registry<K, V>> {
+		self.shards[shard].read().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl<K, V, S> ConcurrentRegistry<K, V, S>
+where
+	K: Eq + Hash,
+	S: BuildHasher
+{
+
+	/// Replaces value of existing key in place, its handle stays valid
+	pub fn set(&self, key: K, value: V) -> Handle<V>
+	where K: Clone {
+		let shard = self.shard_of(&key);
+		let local = self.write_shard(shard, |registry| registry.set(key, value));
+		self.global_handle(shard, local)
+	}
+
+	/// Inserts value created by `create` unless key is present.
+	/// `create` is called while the shard of the key is locked
+	pub fn get_or_insert_with(&self, key: K, create: impl FnOnce() -> V) -> Handle<V>
+	where K: Clone {
+		let shard = self.shard_of(&key);
+		if let Some(local) = self.read_shard(shard).get_handle(&key) {
+			return self.global_handle(shard, local);
+		}
+		let local = self.write_shard(shard, |registry| registry.get_or_insert_with(key, create));
+		self.global_handle(shard, local)
+	}
+
+	/// Handles to removed value become stale
+	pub fn remove<Q>(&self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq + ?Sized
+	{
+		self.write_shard(self.shard_of(key), |registry| registry.remove(key))
+	}
+
+	pub fn get<Q>(&self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq + ?Sized,
+		V: Clone
+	{
+		self.read_shard(self.shard_of(key)).get(key).cloned()
+	}
+
+	pub fn get_handle<Q>(&self, key: &Q) -> Option<Handle<V>>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq + ?Sized
+	{
+		let shard = self.shard_of(key);
+		let local = self.read_shard(shard).get_handle(key)?;
+		Some(self.global_handle(shard, local))
+	}
+
+	pub fn contains_key<Q>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq + ?Sized
+	{
+		self.read_shard(self.shard_of(key)).contains_key(key)
+	}
+
+	/// Copies values of all shards at a single point in time.
+	/// Handles created by this registry index the same values in the snapshot
+	pub fn snapshot(&self) -> Registry<K, V>
+	where
+		K: Clone,
+		V: Clone
+	{
+		// All shards are locked at once, writers only ever lock one
+		let shards = (0..self.shards.len()).map(|shard| self.read_shard(shard)).collect::<Vec<_>>();
+		let count = shards.len();
+		let slots_per_shard = shards.iter().map(|shard| shard.slots.len()).max().unwrap_or(0);
+
+		let mut snapshot = Registry::default();
+		for local_index in 0..slots_per_shard {
+			for (shard_index, shard) in shards.iter().enumerate() {
+				let index = local_index * count + shard_index;
+				let slot = match shard.slots.get(local_index) {
+					Some(slot) => Slot { generation: slot.generation, entry: slot.entry.clone() },
+					// Shard has fewer slots than others
+					None => Slot { generation: 0, entry: None }
+				};
+				match &slot.entry {
+					Some((key, _)) => {
+						snapshot.key_to_handle.insert(key.clone(), Handle::new(index, slot.generation));
+					}
+					None => snapshot.free.push(index)
+				}
+				snapshot.slots.push(slot);
+			}
+		}
+		snapshot
+	}
+
+	fn shard_of<Q>(&self, key: &Q) -> usize
+	where Q: Hash + ?Sized {
+		(self.hasher.hash_one(key) % self.shards.len() as u64) as usize
+	}
+
+	fn write_shard<R>(&self, shard: usize, write: impl FnOnce(&mut Registry<K, V>) -> R) -> R {
+		let mut registry = self.shards[shard].write().unwrap_or_else(PoisonError::into_inner);
+		write(&mut registry)
+	}
+}
+
+impl<K, V> From<Registry<K, V>> for ConcurrentRegistry<K, V>
+where K: Eq + Hash + Clone {
+	/// Handles of `registry` are not valid for the returned registry
+	fn from(registry: Registry<K, V>) -> Self {
+		let concurrent = Self::default();
+		for slot in registry.slots {
+			if let Some((key, value)) = slot.entry {
+				concurrent.set(key, value);
+			}
+		}
+		concurrent
+	}
+}
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Size<T> {
 	pub width: T,
 	pub height: T
@@ -0,0 +1,101 @@
+#![cfg(not(loom))]
+
+use std::{num::NonZero, sync::Barrier, thread};
+
+use starflow_util::{ConcurrentRegistry, Registry};
+
+
+const THREADS: usize = 8;
+const KEYS_PER_THREAD: usize = 1000;
+
+#[test]
+fn handles_stay_valid_in_snapshot() {
+	let registry = ConcurrentRegistry::<&str, u32>::new(NonZero::new(4).unwrap());
+	let a = registry.set("a", 1);
+	let b = registry.set("b", 2);
+	let c = registry.set("c", 3);
+	assert_eq!(registry.remove("b"), Some(2));
+	assert_eq!(registry.get_by_handle(b), None);
+
+	let snapshot = registry.snapshot();
+	assert_eq!(snapshot.len(), 2);
+	assert_eq!(snapshot[a], 1);
+	assert_eq!(snapshot[c], 3);
+	assert!(!snapshot.contains_handle(b));
+	assert!(snapshot.get_handle("a") == Some(a));
+
+	// Snapshot is not affected by later changes
+	registry.set("a", 10);
+	assert_eq!(registry.get_by_handle(a), Some(10));
+	assert_eq!(snapshot[a], 1);
+}
+
+#[test]
+fn converts_from_registry() {
+	let registry: Registry<&str, u32> = [("a", 1), ("b", 2)].into_iter().collect();
+	let concurrent = ConcurrentRegistry::from(registry);
+	assert_eq!(concurrent.len(), 2);
+	assert_eq!(concurrent.get("b"), Some(2));
+}
+
+#[test]
+fn concurrent_inserts_and_reads() {
+	let registry = ConcurrentRegistry::<String, usize>::default();
+	let barrier = Barrier::new(THREADS);
+	let handles = thread::scope(|scope| {
+		let workers = (0..THREADS).map(|thread| {
+			let registry = &registry;
+			let barrier = &barrier;
+			scope.spawn(move || {
+				barrier.wait();
+				(0..KEYS_PER_THREAD).map(|index| {
+					let value = thread * KEYS_PER_THREAD + index;
+					let handle = registry.set(value.to_string(), value);
+					// Every other thread reads back keys of all threads
+					let other = (thread + 1) % THREADS * KEYS_PER_THREAD + index;
+					if let Some(read) = registry.get(&other.to_string()) {
+						assert_eq!(read, other);
+					}
+					assert_eq!(registry.get_by_handle(handle), Some(value));
+					(handle, value)
+				}).collect::<Vec<_>>()
+			})
+		}).collect::<Vec<_>>();
+		workers.into_iter()
+			.flat_map(|worker| worker.join().unwrap())
+			.collect::<Vec<_>>()
+	});
+
+	assert_eq!(registry.len(), THREADS * KEYS_PER_THREAD);
+	let snapshot = registry.snapshot();
+	for (handle, value) in handles {
+		assert_eq!(snapshot[handle], value);
+		assert_eq!(snapshot.key_of(handle), Some(&value.to_string()));
+	}
+}
+
+#[test]
+fn concurrent_get_or_insert_creates_once() {
+	let registry = ConcurrentRegistry::<usize, usize>::default();
+	let barrier = Barrier::new(THREADS);
+	let handles = thread::scope(|scope| {
+		let workers = (0..THREADS).map(|thread| {
+			let registry = &registry;
+			let barrier = &barrier;
+			scope.spawn(move || {
+				barrier.wait();
+				(0..KEYS_PER_THREAD)
+					.map(|key| registry.get_or_insert_with(key, || thread))
+					.collect::<Vec<_>>()
+			})
+		}).collect::<Vec<_>>();
+		workers.into_iter()
+			.map(|worker| worker.join().unwrap())
+			.collect::<Vec<_>>()
+	});
+
+	assert_eq!(registry.len(), KEYS_PER_THREAD);
+	for thread_handles in &handles[1..] {
+		assert!(thread_handles == &handles[0]);
+	}
+}
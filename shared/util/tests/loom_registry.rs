@@ -0,0 +1,102 @@
+//! Run with `RUSTFLAGS="--cfg loom" cargo test -p starflow-util --test loom_registry --release`
+#![cfg(loom)]
+
+use std::{hash::{BuildHasherDefault, Hasher}, num::NonZero};
+
+use loom::{sync::Arc, thread};
+
+use starflow_util::ConcurrentRegistry;
+
+
+/// Odd keys go to one shard and even keys to the other.
+/// Loom replays executions, so shards must not depend on random seeds
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 = self.0 << 8 | *byte as u64;
+		}
+	}
+
+	fn write_u32(&mut self, key: u32) {
+		self.0 = key as u64;
+	}
+}
+
+type Registry = ConcurrentRegistry<u32, u32, BuildHasherDefault<KeyHasher>>;
+
+fn registry() -> Arc<Registry> {
+	Arc::new(Registry::with_hasher(NonZero::new(2).unwrap(), BuildHasherDefault::default()))
+}
+
+#[test]
+fn concurrent_sets_get_distinct_handles() {
+	loom::model(|| {
+		let registry = registry();
+		let other = registry.clone();
+		// Same shard
+		let thread = thread::spawn(move || other.set(1, 10));
+		let a = registry.set(3, 30);
+		let b = thread.join().unwrap();
+
+		assert!(a != b);
+		assert_eq!(registry.get_by_handle(a), Some(30));
+		assert_eq!(registry.get_by_handle(b), Some(10));
+	});
+}
+
+#[test]
+fn concurrent_get_or_insert_agrees() {
+	loom::model(|| {
+		let registry = registry();
+		let other = registry.clone();
+		let thread = thread::spawn(move || other.get_or_insert_with(1, || 10));
+		let a = registry.get_or_insert_with(1, || 20);
+		let b = thread.join().unwrap();
+
+		assert!(a == b);
+		assert_eq!(registry.len(), 1);
+	});
+}
+
+#[test]
+fn removal_races_with_read() {
+	loom::model(|| {
+		let registry = registry();
+		let handle = registry.set(1, 10);
+		let other = registry.clone();
+		let thread = thread::spawn(move || other.get_by_handle(handle));
+		assert_eq!(registry.remove(&1), Some(10));
+		let read = thread.join().unwrap();
+
+		assert!(read.is_none() || read == Some(10));
+		assert_eq!(registry.get_by_handle(handle), None);
+	});
+}
+
+#[test]
+fn snapshot_is_consistent() {
+	loom::model(|| {
+		let registry = registry();
+		let first = registry.set(2, 20);
+		let other = registry.clone();
+		// Moves value between shards, snapshot must never see it in both
+		let thread = thread::spawn(move || {
+			let value = other.remove(&2).unwrap();
+			other.set(1, value);
+		});
+		let snapshot = registry.snapshot();
+		thread.join().unwrap();
+
+		assert!(snapshot.len() <= 1);
+		if snapshot.contains_handle(first) {
+			assert_eq!(snapshot.len(), 1);
+		}
+	});
+}
@@ -0,0 +1,76 @@
+use starflow_util::Registry;
+
+
+#[test]
+fn replaced_value_keeps_handle() {
+	let mut registry = Registry::<&str, u32>::default();
+	let handle = registry.set("a", 1);
+	assert!(registry.set("a", 2) == handle);
+	assert_eq!(registry[handle], 2);
+	assert_eq!(registry.len(), 1);
+}
+
+#[test]
+fn removed_handle_is_stale() {
+	let mut registry = Registry::<&str, u32>::default();
+	let a = registry.set("a", 1);
+	let b = registry.set("b", 2);
+	assert_eq!(registry.remove("a"), Some(1));
+	assert_eq!(registry.remove("a"), None);
+	assert_eq!(registry.get("a"), None);
+	assert_eq!(registry.get_by_handle(a), None);
+	assert_eq!(registry.get_by_handle(b), Some(&2));
+
+	// Slot is reused with a new generation
+	let c = registry.set("c", 3);
+	assert!(c != a);
+	assert_eq!(registry.get_by_handle(a), None);
+	assert_eq!(registry[c], 3);
+	assert_eq!(registry.len(), 2);
+}
+
+#[test]
+fn iterates_live_values() {
+	let mut registry = Registry::<&str, u32>::default();
+	let a = registry.set("a", 1);
+	registry.set("b", 2);
+	let c = registry.set("c", 3);
+	registry.remove("b");
+	let (handles, values): (Vec<_>, Vec<&u32>) = registry.iter().unzip();
+	assert!(handles == [a, c]);
+	assert_eq!(values, [&1, &3]);
+}
+
+#[test]
+#[should_panic(expected = "Stale registry handle")]
+fn indexing_stale_handle_panics() {
+	let mut registry = Registry::<&str, u32>::default();
+	let handle = registry.set("a", 1);
+	registry.remove("a");
+	let _ = registry[handle];
+}
+
+#[test]
+fn looks_up_key_of_handle() {
+	let mut registry: Registry<&str, u32> = [("a", 1), ("b", 2)].into_iter().collect();
+	let a = registry.get_handle("a").unwrap();
+	assert_eq!(registry.key_of(a), Some(&"a"));
+	registry.remove("a");
+	assert_eq!(registry.key_of(a), None);
+	assert!(!registry.contains_key("a"));
+	assert_eq!(registry.keys().collect::<Vec<_>>(), [&"b"]);
+}
+
+#[test]
+fn inserts_only_missing_keys() {
+	let mut registry = Registry::<&str, u32>::default();
+	let a = registry.get_or_insert_with("a", || 1);
+	assert!(registry.get_or_insert_with("a", || unreachable!()) == a);
+	*registry.get_mut("a").unwrap() += 1;
+	for (_, value) in registry.iter_mut() {
+		*value *= 10;
+	}
+	assert_eq!(registry[a], 20);
+	assert_eq!(format!("{:?}", registry), r#"{"a": 20}"#);
+	assert_eq!(format!("{:?}", a), "Handle(0v0)");
+}